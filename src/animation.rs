@@ -0,0 +1,276 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+use gif::{Encoder, EncodingError, Frame, Repeat};
+use log::info;
+
+use super::galaxy::{Actor, Point2};
+
+/// Palette index of the background, the remaining entries are a 6x6x6 color
+/// cube every body color gets snapped to.
+const BACKGROUND: u8 = 0;
+const BACKGROUND_RGB: [u8; 3] = [30, 40, 40];
+const CUBE_LEVELS: u32 = 6;
+
+fn palette() -> Vec<u8> {
+    let mut palette = BACKGROUND_RGB.to_vec();
+    for i in 0..CUBE_LEVELS.pow(3) {
+        let (r, g, b) = (i / 36, i / 6 % 6, i % 6);
+        palette.extend([r, g, b].iter().map(|c| (c * 51) as u8));
+    }
+    palette
+}
+
+/// The palette entry closest to an RGBA color as stored in `Actor::color`.
+fn palette_index(color: u32) -> u8 {
+    let level = |shift: u32| ((color >> shift & 0xff) + 25) / 51;
+    (1 + level(24) * 36 + level(16) * 6 + level(8)) as u8
+}
+
+fn to_io(e: EncodingError) -> io::Error {
+    match e {
+        EncodingError::Io(e) => e,
+        e => io::Error::new(io::ErrorKind::InvalidData, e),
+    }
+}
+
+/// Renders bodies into the frames of an animated GIF.
+///
+/// Frames are drawn in software, so this works without a window. The GIF is
+/// only complete once `finish` was called.
+pub struct GifRecorder<W: Write> {
+    encoder: Encoder<W>,
+    width: u16,
+    height: u16,
+    /// Time between frames in hundredths of a second.
+    delay: u16,
+    pixels: Vec<u8>,
+    frames: u32,
+}
+
+impl<W: Write> GifRecorder<W> {
+    pub fn new(writer: W, width: u16, height: u16, delay: u16) -> io::Result<Self> {
+        let mut encoder = Encoder::new(writer, width, height, &palette()).map_err(to_io)?;
+        encoder.set_repeat(Repeat::Infinite).map_err(to_io)?;
+        Ok(GifRecorder {
+            encoder,
+            width,
+            height,
+            delay,
+            pixels: vec![BACKGROUND; width as usize * height as usize],
+            frames: 0,
+        })
+    }
+
+    /// Draw `bodies` as seen from a camera at `center`, a `zoom` of 1 maps one
+    /// unit of space onto one pixel.
+    pub fn add_frame(&mut self, bodies: &[Actor], center: Point2, zoom: f32) -> io::Result<()> {
+        self.pixels.fill(BACKGROUND);
+        let (w, h) = (self.width as f32, self.height as f32);
+        for body in bodies {
+            let x = (body.pos.x - center.x) * zoom + w / 2.0;
+            let y = -(body.pos.y - center.y) * zoom + h / 2.0;
+            // Like on screen every body is at least a pixel wide.
+            let r = (body.radius * zoom).max(0.5);
+            let color = palette_index(body.color);
+            let rows = (y - r).floor().max(0.0) as usize..(y + r).ceil().min(h).max(0.0) as usize;
+            let cols = (x - r).floor().max(0.0) as usize..(x + r).ceil().min(w).max(0.0) as usize;
+            for row in rows {
+                for col in cols.clone() {
+                    let (dx, dy) = (col as f32 + 0.5 - x, row as f32 + 0.5 - y);
+                    if dx * dx + dy * dy <= r * r {
+                        self.pixels[row * self.width as usize + col] = color;
+                    }
+                }
+            }
+        }
+        let mut frame = Frame::from_indexed_pixels(self.width, self.height, &self.pixels, None);
+        frame.delay = self.delay;
+        self.encoder.write_frame(&frame).map_err(to_io)?;
+        self.frames += 1;
+        Ok(())
+    }
+
+    pub fn frames(&self) -> u32 {
+        self.frames
+    }
+
+    /// Write the end of the GIF and hand back the writer.
+    pub fn finish(self) -> io::Result<W> {
+        let mut writer = self.encoder.into_inner()?;
+        writer.flush()?;
+        Ok(writer)
+    }
+}
+
+/// Where the camera looks at a moment of simulated time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Keyframe {
+    pub time: f32,
+    pub center: Point2,
+    pub zoom: f32,
+}
+
+const CAMERA_PATH_HEADER: [&str; 4] = ["time", "x", "y", "zoom"];
+
+/// Keyframes the camera flies through, no matter what the bodies do. It eases
+/// in and out of every keyframe and zooms at a steady rate in between.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CameraPath {
+    keyframes: Vec<Keyframe>,
+}
+
+impl CameraPath {
+    /// Keyframes from CSV rows of `time,x,y,zoom`, like a star catalog.
+    ///
+    /// Empty lines, `#` comments and a header naming the columns are skipped.
+    /// Any other row which is not four finite numbers with a positive zoom,
+    /// at a later time than the row before, is an error naming its line.
+    pub fn parse(csv: &str) -> Result<CameraPath, String> {
+        let mut keyframes: Vec<Keyframe> = Vec::new();
+        for (i, line) in csv.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            if keyframes.is_empty() && fields == CAMERA_PATH_HEADER {
+                continue;
+            }
+            let row: Vec<f32> = fields
+                .iter()
+                .map(|f| f.parse::<f32>().ok().filter(|v| v.is_finite()))
+                .collect::<Option<_>>()
+                .ok_or_else(|| format!("line {}: not a number in '{}'", i + 1, line))?;
+            let [time, x, y, zoom] = row[..] else {
+                return Err(format!(
+                    "line {}: expected 4 columns time,x,y,zoom, got {}",
+                    i + 1,
+                    row.len()
+                ));
+            };
+            if zoom <= 0.0 {
+                return Err(format!("line {}: zoom must be positive", i + 1));
+            }
+            if keyframes.last().is_some_and(|k| time <= k.time) {
+                return Err(format!(
+                    "line {}: time must be after the line before",
+                    i + 1
+                ));
+            }
+            keyframes.push(Keyframe {
+                time,
+                center: Point2::new(x, y),
+                zoom,
+            });
+        }
+        if keyframes.is_empty() {
+            return Err("no keyframes".to_string());
+        }
+        Ok(CameraPath { keyframes })
+    }
+
+    /// Read a camera path, see `parse`.
+    pub fn load(path: &Path) -> io::Result<CameraPath> {
+        let csv = fs::read_to_string(path)?;
+        let camera_path = CameraPath::parse(&csv).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: {}", path.display(), e),
+            )
+        })?;
+        info!(
+            "loaded {} keyframes from {}",
+            camera_path.keyframes.len(),
+            path.display()
+        );
+        Ok(camera_path)
+    }
+
+    /// Center and zoom at `time`, held at the first keyframe before it and
+    /// `None` after the last, when the camera is free again.
+    pub fn at(&self, time: f32) -> Option<(Point2, f32)> {
+        let next = self.keyframes.iter().position(|k| k.time >= time)?;
+        let to = self.keyframes[next];
+        let Some(from) = next.checked_sub(1).map(|i| self.keyframes[i]) else {
+            return Some((to.center, to.zoom));
+        };
+        let t = (time - from.time) / (to.time - from.time);
+        let eased = t * t * (3.0 - 2.0 * t);
+        let center = from.center.lerp(to.center, eased);
+        let zoom = from.zoom * (to.zoom / from.zoom).powf(eased);
+        Some((center, zoom))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::galaxy::{create_suns, PhysicsProfile, SimConfig, Simulation};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn test_gif_has_requested_frames() {
+        let mut rng = StdRng::seed_from_u64(2);
+        let suns = create_suns(4, 30.0, &PhysicsProfile::ARCADE, true, &mut rng);
+        let mut sim = Simulation::new(suns, SimConfig::default());
+        let mut recorder = GifRecorder::new(Vec::new(), 40, 30, 2).unwrap();
+        for _ in 0..5 {
+            recorder.add_frame(sim.actors(), Point2::ZERO, 0.5).unwrap();
+            sim.step(1.0 / 60.0);
+        }
+        let bytes = recorder.finish().unwrap();
+
+        let mut decoder = gif::DecodeOptions::new().read_info(&bytes[..]).unwrap();
+        assert_eq!((decoder.width(), decoder.height()), (40, 30));
+        let mut frames = 0;
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            assert_eq!(frame.delay, 2);
+            frames += 1;
+        }
+        assert_eq!(frames, 5);
+        assert_eq!(bytes.last(), Some(&0x3b));
+    }
+
+    #[test]
+    fn test_camera_path_hits_keyframes() {
+        let path =
+            CameraPath::parse("time,x,y,zoom\n# fly in\n1,0,0,1\n\n3,100,-50,4\n4.5,100,50,0.5\n")
+                .unwrap();
+        let expected = [
+            (0.0, (0.0, 0.0), 1.0),
+            (1.0, (0.0, 0.0), 1.0),
+            (3.0, (100.0, -50.0), 4.0),
+            (4.5, (100.0, 50.0), 0.5),
+        ];
+        for (time, (x, y), zoom) in expected {
+            let (center, z) = path.at(time).unwrap();
+            assert!(
+                center.distance(Point2::new(x, y)) < 1e-3,
+                "{} at {}",
+                center,
+                time
+            );
+            assert!((z - zoom).abs() < 1e-4, "{} at {}", z, time);
+        }
+        // halfway from 1 to 3 s, the zoom is halfway from 1 to 4 on a log scale
+        let (center, zoom) = path.at(2.0).unwrap();
+        assert!(center.distance(Point2::new(50.0, -25.0)) < 1e-3);
+        assert!((zoom - 2.0).abs() < 1e-4);
+        assert_eq!(path.at(4.6), None);
+
+        assert!(CameraPath::parse("").is_err());
+        assert!(CameraPath::parse("1,0,0,1\n1,0,0,1").is_err());
+        assert!(CameraPath::parse("1,0,0,0").is_err());
+    }
+
+    #[test]
+    fn test_palette_index() {
+        let palette = palette();
+        let rgb = |i: u8| &palette[i as usize * 3..i as usize * 3 + 3];
+        assert_eq!(rgb(palette_index(0xffff_ffff)), [255, 255, 255]);
+        assert_eq!(rgb(palette_index(0x0000_00ff)), [0, 0, 0]);
+        assert_eq!(rgb(palette_index(0x9bb0_ffff)), [153, 153, 255]);
+    }
+}
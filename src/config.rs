@@ -0,0 +1,388 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use super::game::{Comparison, Settings, TraceStyle};
+use super::theme::{HexColor, Theme, ThemePreset};
+use nbody::export::CatalogUnits;
+use nbody::galaxy::DEFAULT_TRACE_INTERVAL;
+use nbody::galaxy::{ClassCounts, CollisionMode, Imf, Mode, PhysicsProfile, SimConfig};
+use nbody::units::UnitSystem;
+
+const DEFAULT_SUNS: u32 = 3;
+const DEFAULT_FPS: u32 = 60;
+const DEFAULT_RING_COUNT: u32 = 8;
+const DEFAULT_RING_RADIUS: f32 = 150.0;
+const DEFAULT_RING_MASS: f32 = 100.0;
+const DEFAULT_SLINGSHOT_THRESHOLD: f32 = 0.25;
+const DEFAULT_MAX_BODIES: u32 = 500;
+const DEFAULT_POTENTIAL_GRID: u32 = 64;
+const DEFAULT_CIRCLE_QUALITY: f32 = 1.0;
+const DEFAULT_CAMERA_SMOOTHING: f32 = 0.1;
+const DEFAULT_INSPECT_COUNT: usize = 3;
+const DEFAULT_CHECKPOINT_KEEP: usize = 3;
+const DEFAULT_TRAJECTORY_INTERVAL: f32 = 0.1;
+const DEFAULT_GIF_FRAMES: u32 = 120;
+const DEFAULT_GIF_SCALE: u32 = 2;
+
+/// Options which can be given on the command line or in a config file.
+///
+/// Anything left out is `None`. `or` stacks several sources on top of each
+/// other, whatever is still unset in the end gets its built-in default.
+#[derive(Debug, Clone, Default, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+    pub suns: Option<u32>,
+    pub class_counts: Option<ClassCounts>,
+    pub black_holes: Option<u32>,
+    pub collision_scale: Option<f32>,
+    pub collision: Option<CollisionMode>,
+    pub fragment_energy: Option<f32>,
+    pub force_exponent: Option<f32>,
+    pub supernova_mass: Option<f32>,
+    pub accretion: Option<bool>,
+    pub lock_barycenter: Option<bool>,
+    pub max_speed: Option<f32>,
+    pub gw_inspiral: Option<f32>,
+    pub mode: Option<Mode>,
+    pub seed: Option<u64>,
+    pub fps: Option<u32>,
+    pub vsync: Option<bool>,
+    pub paused: Option<bool>,
+    pub output: Option<PathBuf>,
+    pub svg: Option<PathBuf>,
+    pub trajectory: Option<PathBuf>,
+    pub trajectory_interval: Option<f32>,
+    pub replay: Option<PathBuf>,
+    pub replay_loop: Option<bool>,
+    pub compare: Option<PathBuf>,
+    pub checkpoint_interval: Option<f32>,
+    pub checkpoint_keep: Option<usize>,
+    pub checkpoint_dir: Option<PathBuf>,
+    pub load: Option<PathBuf>,
+    pub catalog: Option<PathBuf>,
+    pub period_seconds: Option<f32>,
+    pub inspect_count: Option<usize>,
+    pub unit_mass: Option<f32>,
+    pub unit_length: Option<f32>,
+    pub unit_time: Option<f32>,
+    pub catalog_mass_unit: Option<f32>,
+    pub catalog_length_unit: Option<f32>,
+    pub catalog_velocity_unit: Option<f32>,
+    pub steps: Option<u64>,
+    pub events: Option<bool>,
+    pub recenter_momentum: Option<bool>,
+    pub pin_heaviest: Option<bool>,
+    pub trace_style: Option<TraceStyle>,
+    pub trace_interval: Option<u32>,
+    pub trace_budget: Option<usize>,
+    pub glow: Option<bool>,
+    pub sprites: Option<bool>,
+    pub sound: Option<bool>,
+    pub shake: Option<bool>,
+    pub potential_field: Option<bool>,
+    pub potential_grid: Option<u32>,
+    pub circle_quality: Option<f32>,
+    pub tilt: Option<f32>,
+    pub zoom_smoothing: Option<f32>,
+    pub pan_smoothing: Option<f32>,
+    pub spawn_rate: Option<f32>,
+    pub max_bodies: Option<u32>,
+    pub theme: Option<ThemePreset>,
+    pub background: Option<HexColor>,
+    pub slingshot_threshold: Option<f32>,
+    pub ring_count: Option<u32>,
+    pub ring_radius: Option<f32>,
+    pub ring_mass: Option<f32>,
+    pub galaxy_radius: Option<f32>,
+    pub max_velocity: Option<f32>,
+    pub cold: Option<bool>,
+    pub imf: Option<Imf>,
+    pub gif: Option<PathBuf>,
+    pub gif_frames: Option<u32>,
+    pub gif_scale: Option<u32>,
+    pub camera_path: Option<PathBuf>,
+}
+
+impl Config {
+    pub fn from_toml(toml: &str) -> Result<Config, String> {
+        toml::from_str(toml).map_err(|e| e.to_string())
+    }
+
+    pub fn from_file(path: &Path) -> Result<Config, String> {
+        let toml = fs::read_to_string(path)
+            .map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+        Config::from_toml(&toml).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    /// Take every option not set in `self` from `fallback`.
+    pub fn or(self, fallback: Config) -> Config {
+        Config {
+            suns: self.suns.or(fallback.suns),
+            class_counts: self.class_counts.or(fallback.class_counts),
+            black_holes: self.black_holes.or(fallback.black_holes),
+            collision_scale: self.collision_scale.or(fallback.collision_scale),
+            collision: self.collision.or(fallback.collision),
+            fragment_energy: self.fragment_energy.or(fallback.fragment_energy),
+            force_exponent: self.force_exponent.or(fallback.force_exponent),
+            supernova_mass: self.supernova_mass.or(fallback.supernova_mass),
+            accretion: self.accretion.or(fallback.accretion),
+            lock_barycenter: self.lock_barycenter.or(fallback.lock_barycenter),
+            max_speed: self.max_speed.or(fallback.max_speed),
+            gw_inspiral: self.gw_inspiral.or(fallback.gw_inspiral),
+            mode: self.mode.or(fallback.mode),
+            seed: self.seed.or(fallback.seed),
+            fps: self.fps.or(fallback.fps),
+            vsync: self.vsync.or(fallback.vsync),
+            paused: self.paused.or(fallback.paused),
+            output: self.output.or(fallback.output),
+            svg: self.svg.or(fallback.svg),
+            trajectory: self.trajectory.or(fallback.trajectory),
+            trajectory_interval: self.trajectory_interval.or(fallback.trajectory_interval),
+            replay: self.replay.or(fallback.replay),
+            replay_loop: self.replay_loop.or(fallback.replay_loop),
+            compare: self.compare.or(fallback.compare),
+            checkpoint_interval: self.checkpoint_interval.or(fallback.checkpoint_interval),
+            checkpoint_keep: self.checkpoint_keep.or(fallback.checkpoint_keep),
+            checkpoint_dir: self.checkpoint_dir.or(fallback.checkpoint_dir),
+            load: self.load.or(fallback.load),
+            catalog: self.catalog.or(fallback.catalog),
+            period_seconds: self.period_seconds.or(fallback.period_seconds),
+            inspect_count: self.inspect_count.or(fallback.inspect_count),
+            unit_mass: self.unit_mass.or(fallback.unit_mass),
+            unit_length: self.unit_length.or(fallback.unit_length),
+            unit_time: self.unit_time.or(fallback.unit_time),
+            catalog_mass_unit: self.catalog_mass_unit.or(fallback.catalog_mass_unit),
+            catalog_length_unit: self.catalog_length_unit.or(fallback.catalog_length_unit),
+            catalog_velocity_unit: self
+                .catalog_velocity_unit
+                .or(fallback.catalog_velocity_unit),
+            steps: self.steps.or(fallback.steps),
+            events: self.events.or(fallback.events),
+            recenter_momentum: self.recenter_momentum.or(fallback.recenter_momentum),
+            pin_heaviest: self.pin_heaviest.or(fallback.pin_heaviest),
+            trace_style: self.trace_style.or(fallback.trace_style),
+            trace_interval: self.trace_interval.or(fallback.trace_interval),
+            trace_budget: self.trace_budget.or(fallback.trace_budget),
+            glow: self.glow.or(fallback.glow),
+            sprites: self.sprites.or(fallback.sprites),
+            sound: self.sound.or(fallback.sound),
+            shake: self.shake.or(fallback.shake),
+            potential_field: self.potential_field.or(fallback.potential_field),
+            potential_grid: self.potential_grid.or(fallback.potential_grid),
+            circle_quality: self.circle_quality.or(fallback.circle_quality),
+            tilt: self.tilt.or(fallback.tilt),
+            zoom_smoothing: self.zoom_smoothing.or(fallback.zoom_smoothing),
+            pan_smoothing: self.pan_smoothing.or(fallback.pan_smoothing),
+            spawn_rate: self.spawn_rate.or(fallback.spawn_rate),
+            max_bodies: self.max_bodies.or(fallback.max_bodies),
+            theme: self.theme.or(fallback.theme),
+            background: self.background.or(fallback.background),
+            slingshot_threshold: self.slingshot_threshold.or(fallback.slingshot_threshold),
+            ring_count: self.ring_count.or(fallback.ring_count),
+            ring_radius: self.ring_radius.or(fallback.ring_radius),
+            ring_mass: self.ring_mass.or(fallback.ring_mass),
+            galaxy_radius: self.galaxy_radius.or(fallback.galaxy_radius),
+            max_velocity: self.max_velocity.or(fallback.max_velocity),
+            cold: self.cold.or(fallback.cold),
+            imf: self.imf.or(fallback.imf),
+            gif: self.gif.or(fallback.gif),
+            gif_frames: self.gif_frames.or(fallback.gif_frames),
+            gif_scale: self.gif_scale.or(fallback.gif_scale),
+            camera_path: self.camera_path.or(fallback.camera_path),
+        }
+    }
+
+    pub fn sim_config(&self) -> SimConfig {
+        let default = SimConfig::default();
+        let profile = self.mode.map_or(default.profile, Mode::profile);
+        let max_velocity = self.max_velocity.unwrap_or(profile.max_starting_velocity);
+        SimConfig {
+            collision_scale: self.collision_scale.unwrap_or(default.collision_scale),
+            collision: self.collision.unwrap_or(default.collision),
+            fragment_energy: self.fragment_energy.unwrap_or(default.fragment_energy),
+            force_exponent: self.force_exponent.unwrap_or(default.force_exponent),
+            supernova_mass: self.supernova_mass.or(default.supernova_mass),
+            accretion: self.accretion.unwrap_or(default.accretion),
+            lock_barycenter: self.lock_barycenter.unwrap_or(default.lock_barycenter),
+            max_speed: self.max_speed.or(default.max_speed),
+            inspiral: self.gw_inspiral.or(default.inspiral),
+            profile: PhysicsProfile {
+                max_starting_velocity: max_velocity,
+                imf: self.imf.unwrap_or(profile.imf),
+                ..profile
+            },
+        }
+    }
+
+    /// Steps of a run without window, if one was asked for. Recording a GIF
+    /// runs just long enough for its frames unless `steps` says otherwise.
+    pub fn headless_steps(&self) -> Option<u64> {
+        let gif_frames = self.gif_frames.unwrap_or(DEFAULT_GIF_FRAMES) as u64;
+        self.steps.or_else(|| self.gif.as_ref().map(|_| gif_frames))
+    }
+
+    /// The simulation to show next to this one with `compare`: the options
+    /// of that file on top of these ones.
+    pub fn comparison(&self) -> Result<Option<Comparison>, String> {
+        let Some(path) = &self.compare else {
+            return Ok(None);
+        };
+        let config = Config::from_file(path)?.or(self.clone()).sim_config();
+        let label = path.file_stem().map_or_else(
+            || path.display().to_string(),
+            |s| s.to_string_lossy().into_owned(),
+        );
+        Ok(Some(Comparison { label, config }))
+    }
+
+    /// Physical units to display, if any factor for them was given.
+    fn units(&self) -> Option<UnitSystem> {
+        let factors = [self.unit_mass, self.unit_length, self.unit_time];
+        if factors.iter().all(Option::is_none) {
+            return None;
+        }
+        let default = UnitSystem::default();
+        Some(UnitSystem {
+            mass: self.unit_mass.unwrap_or(default.mass),
+            length: self.unit_length.unwrap_or(default.length),
+            time: self.unit_time.unwrap_or(default.time),
+        })
+    }
+
+    /// The game settings, a random seed is drawn if none was given.
+    pub fn settings(&self) -> Settings {
+        let units = CatalogUnits::default();
+        let theme = self.theme.unwrap_or(ThemePreset::Dark).theme();
+        Settings {
+            suns: self.suns.unwrap_or(DEFAULT_SUNS),
+            class_counts: self.class_counts.clone(),
+            black_holes: self.black_holes.unwrap_or(0),
+            seed: self.seed.unwrap_or_else(rand::random),
+            fps: self.fps.unwrap_or(DEFAULT_FPS),
+            vsync: self.vsync.unwrap_or(true),
+            paused: self.paused.unwrap_or(false),
+            output: self.output.clone(),
+            svg: self.svg.clone(),
+            trajectory: self.trajectory.clone(),
+            trajectory_interval: self
+                .trajectory_interval
+                .unwrap_or(DEFAULT_TRAJECTORY_INTERVAL),
+            replay: self.replay.clone(),
+            replay_loop: self.replay_loop.unwrap_or(false),
+            compare: None,
+            checkpoint_interval: self.checkpoint_interval,
+            checkpoint_keep: self.checkpoint_keep.unwrap_or(DEFAULT_CHECKPOINT_KEEP),
+            checkpoint_dir: self
+                .checkpoint_dir
+                .clone()
+                .unwrap_or_else(|| PathBuf::from(".")),
+            load: self.load.clone(),
+            catalog: self.catalog.clone(),
+            catalog_units: CatalogUnits {
+                mass: self.catalog_mass_unit.unwrap_or(units.mass),
+                length: self.catalog_length_unit.unwrap_or(units.length),
+                velocity: self.catalog_velocity_unit.unwrap_or(units.velocity),
+            },
+            units: self.units(),
+            period_seconds: self.period_seconds,
+            inspect_count: self.inspect_count.unwrap_or(DEFAULT_INSPECT_COUNT),
+            recenter_momentum: self.recenter_momentum.unwrap_or(true),
+            pin_heaviest: self.pin_heaviest.unwrap_or(false),
+            cold: self.cold.unwrap_or(false),
+            theme: Theme {
+                background: self.background.map_or(theme.background, |c| c.0),
+                traces: self.trace_style.unwrap_or(theme.traces),
+                ..theme
+            },
+            ring_count: self.ring_count.unwrap_or(DEFAULT_RING_COUNT),
+            ring_radius: self.ring_radius.unwrap_or(DEFAULT_RING_RADIUS),
+            ring_mass: self.ring_mass.unwrap_or(DEFAULT_RING_MASS),
+            trace_interval: self.trace_interval.unwrap_or(DEFAULT_TRACE_INTERVAL),
+            trace_budget: self.trace_budget,
+            glow: self.glow.unwrap_or(false),
+            sprites: self.sprites.unwrap_or(false),
+            sound: self.sound.unwrap_or(false),
+            shake: self.shake.unwrap_or(false),
+            potential_field: self.potential_field.unwrap_or(false),
+            potential_grid: self.potential_grid.unwrap_or(DEFAULT_POTENTIAL_GRID),
+            circle_quality: self.circle_quality.unwrap_or(DEFAULT_CIRCLE_QUALITY),
+            tilt: self.tilt.unwrap_or(0.0),
+            zoom_smoothing: self.zoom_smoothing.unwrap_or(DEFAULT_CAMERA_SMOOTHING),
+            pan_smoothing: self.pan_smoothing.unwrap_or(DEFAULT_CAMERA_SMOOTHING),
+            events: self.events.unwrap_or(false),
+            spawn_rate: self.spawn_rate,
+            max_bodies: self.max_bodies.unwrap_or(DEFAULT_MAX_BODIES),
+            slingshot_threshold: self
+                .slingshot_threshold
+                .unwrap_or(DEFAULT_SLINGSHOT_THRESHOLD),
+            galaxy_radius: self.galaxy_radius,
+            gif: self.gif.clone(),
+            gif_frames: self.gif_frames.unwrap_or(DEFAULT_GIF_FRAMES),
+            gif_scale: self.gif_scale.unwrap_or(DEFAULT_GIF_SCALE),
+            camera_path: self.camera_path.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = r#"
+suns = 12
+seed = 42
+collision = "merge"
+force-exponent = 1.5
+fps = 120
+"#;
+
+    #[test]
+    fn test_command_line_overrides_file() {
+        let file = Config::from_toml(SAMPLE).unwrap();
+        let cli = Config {
+            suns: Some(5),
+            fps: Some(30),
+            ..Config::default()
+        };
+        let config = cli.or(file);
+        let (settings, sim) = (config.settings(), config.sim_config());
+        assert_eq!(settings.suns, 5);
+        assert_eq!(settings.fps, 30);
+        assert_eq!(settings.seed, 42);
+        assert_eq!(sim.collision, CollisionMode::Merge);
+        assert_eq!(sim.force_exponent, 1.5);
+        assert_eq!(sim.collision_scale, SimConfig::default().collision_scale);
+        assert_eq!(settings.black_holes, 0);
+        assert!(settings.vsync);
+        assert!(!settings.paused);
+        assert!(settings.recenter_momentum);
+    }
+
+    #[test]
+    fn test_max_velocity_overrides_mode() {
+        let config = Config::from_toml("mode = \"realistic\"\nmax-velocity = 7.5").unwrap();
+        let profile = config.sim_config().profile;
+        assert_eq!(profile.max_starting_velocity, 7.5);
+        assert_eq!(profile.g, PhysicsProfile::REALISTIC.g);
+    }
+
+    #[test]
+    fn test_theme_with_overrides() {
+        let file = Config::from_toml("theme = \"paper\"\nbackground = \"#ffffff\"").unwrap();
+        let theme = file.settings().theme;
+        assert_eq!(theme.background, 0xffff_ffff);
+        assert_eq!(theme.hud, ThemePreset::Paper.theme().hud);
+        let cli = Config {
+            trace_style: Some(TraceStyle::Rainbow),
+            ..Config::default()
+        };
+        assert_eq!(cli.or(file).settings().theme.traces, TraceStyle::Rainbow);
+    }
+
+    #[test]
+    fn test_unknown_option_is_an_error() {
+        assert!(Config::from_toml("planets = 3").is_err());
+    }
+}
@@ -0,0 +1,654 @@
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::thread::{self, JoinHandle};
+
+use log::info;
+use serde::{Deserialize, Serialize};
+
+use super::galaxy::{kinetic_energy, new_sun, potential_energy, Actor, EventCounters, Point2};
+use super::galaxy::{new_black_hole, ActorType, PhysicsProfile, Simulation, Vector2};
+
+/// How the view looked when a scenario was saved, so loading it drops you
+/// right where you left off.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CameraState {
+    pub center: Point2,
+    pub zoom: f32,
+    pub speed: f32,
+    pub running: bool,
+    pub show_traces: bool,
+}
+
+impl Default for CameraState {
+    fn default() -> Self {
+        CameraState {
+            center: Point2::ZERO,
+            zoom: 1.0,
+            speed: 1.0,
+            running: true,
+            show_traces: true,
+        }
+    }
+}
+
+/// Bodies to start from, as written by `write_json`.
+#[derive(Deserialize)]
+pub struct Scenario {
+    pub bodies: Vec<Actor>,
+    /// The default camera for files written without one.
+    #[serde(default)]
+    pub camera: CameraState,
+}
+
+#[derive(Serialize)]
+struct Diagnostics {
+    steps: u64,
+    time: f32,
+    #[serde(flatten)]
+    events: EventCounters,
+    kinetic_energy: f32,
+    potential_energy: f32,
+    total_energy: f32,
+}
+
+#[derive(Serialize)]
+struct Output<'a> {
+    bodies: &'a [Actor],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    camera: Option<&'a CameraState>,
+    diagnostics: Diagnostics,
+}
+
+fn output<'a>(sim: &'a Simulation, camera: Option<&'a CameraState>) -> Output<'a> {
+    let bodies = sim.actors();
+    let (ke, pe) = (
+        kinetic_energy(bodies),
+        potential_energy(bodies, sim.config()),
+    );
+    Output {
+        bodies,
+        camera,
+        diagnostics: Diagnostics {
+            steps: sim.steps(),
+            time: sim.time(),
+            events: *sim.counters(),
+            kinetic_energy: ke,
+            potential_energy: pe,
+            total_energy: ke + pe,
+        },
+    }
+}
+
+/// Write into a temporary file next to `path` which only replaces `path` once
+/// it is complete, so a crash midway never leaves a truncated file behind.
+pub(crate) fn write_atomically(
+    path: &Path,
+    write: impl FnOnce(&mut BufWriter<File>) -> io::Result<()>,
+) -> io::Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+    let mut writer = BufWriter::new(File::create(&tmp)?);
+    let written = write(&mut writer)
+        .and_then(|()| writer.into_inner().map_err(|e| e.into_error()))
+        .and_then(|file| file.sync_all());
+    match written {
+        Ok(()) => fs::rename(&tmp, path),
+        Err(e) => {
+            let _ = fs::remove_file(&tmp);
+            Err(e)
+        }
+    }
+}
+
+/// Write all bodies of `sim`, the camera if given and some diagnostics about
+/// the run as JSON.
+pub fn write_json(sim: &Simulation, camera: Option<&CameraState>, path: &Path) -> io::Result<()> {
+    write_atomically(path, |writer| {
+        serde_json::to_writer_pretty(writer, &output(sim, camera))?;
+        Ok(())
+    })
+}
+
+/// Read a scenario written by `write_json`, its diagnostics are ignored.
+pub fn read_scenario(path: &Path) -> io::Result<Scenario> {
+    let reader = BufReader::new(File::open(path)?);
+    Ok(serde_json::from_reader(reader)?)
+}
+
+/// Writes the state every `interval` of simulated time into `dir`, keeping
+/// the last `keep` of them: `checkpoint_0.json` is the latest, the higher
+/// numbers are older. Any of them can be read back with `read_scenario`.
+///
+/// The state is serialized right away but written on a thread of its own, so
+/// the disk never holds up a frame. A checkpoint due while the previous one
+/// is still being written waits for the next call.
+pub struct Checkpoints {
+    dir: PathBuf,
+    interval: f32,
+    keep: usize,
+    next: f32,
+    writer: Option<JoinHandle<io::Result<()>>>,
+}
+
+impl Checkpoints {
+    pub fn new(dir: PathBuf, interval: f32, keep: usize) -> Checkpoints {
+        Checkpoints {
+            dir,
+            interval,
+            keep,
+            next: interval,
+            writer: None,
+        }
+    }
+
+    /// File of the `n`th latest checkpoint, starting at 0.
+    pub fn path(&self, n: usize) -> PathBuf {
+        self.dir.join(format!("checkpoint_{}.json", n))
+    }
+
+    /// Whether a checkpoint is due at simulated `time`.
+    pub fn is_due(&self, time: f32) -> bool {
+        // a simulation started over is back before the last checkpoint
+        time >= self.next || time + self.interval < self.next
+    }
+
+    /// Write a checkpoint of `sim` if one is due, returns whether it did.
+    /// Errors are those of writing the previous checkpoint or serializing
+    /// this one.
+    pub fn update(&mut self, sim: &Simulation, camera: Option<&CameraState>) -> io::Result<bool> {
+        let time = sim.time();
+        if !self.is_due(time) {
+            return Ok(false);
+        }
+        if self.writer.as_ref().is_some_and(|w| !w.is_finished()) {
+            return Ok(false);
+        }
+        self.next = ((time / self.interval).floor() + 1.0) * self.interval;
+        self.wait()?;
+        let json = serde_json::to_vec_pretty(&output(sim, camera))?;
+        let paths: Vec<PathBuf> = (0..self.keep).map(|n| self.path(n)).collect();
+        self.writer = Some(thread::spawn(move || {
+            for n in (1..paths.len()).rev() {
+                match fs::rename(&paths[n - 1], &paths[n]) {
+                    Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+                    _ => (),
+                }
+            }
+            write_atomically(&paths[0], |w| w.write_all(&json))
+        }));
+        Ok(true)
+    }
+
+    /// Wait until the checkpoint being written, if any, is on disk.
+    pub fn wait(&mut self) -> io::Result<()> {
+        match self.writer.take() {
+            Some(writer) => writer.join().expect("checkpoint writer panicked"),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Factors converting the units of a star catalog into simulation units.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CatalogUnits {
+    pub mass: f32,
+    pub length: f32,
+    pub velocity: f32,
+}
+
+impl Default for CatalogUnits {
+    fn default() -> Self {
+        CatalogUnits {
+            mass: 1.0,
+            length: 1.0,
+            velocity: 1.0,
+        }
+    }
+}
+
+const CATALOG_HEADER: [&str; 5] = ["mass", "x", "y", "vx", "vy"];
+
+/// Suns from CSV rows of `mass,x,y,vx,vy` converted with `units`.
+///
+/// Empty lines, `#` comments and a header naming the columns are skipped,
+/// any other row which is not five finite numbers with a positive mass is an
+/// error naming its line.
+pub fn parse_catalog(
+    csv: &str,
+    units: &CatalogUnits,
+    profile: &PhysicsProfile,
+) -> Result<Vec<Actor>, String> {
+    let mut bodies = Vec::new();
+    for (i, line) in csv.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        if bodies.is_empty() && fields == CATALOG_HEADER {
+            continue;
+        }
+        let row: Vec<f32> = fields
+            .iter()
+            .map(|f| f.parse::<f32>().ok().filter(|v| v.is_finite()))
+            .collect::<Option<_>>()
+            .ok_or_else(|| format!("line {}: not a number in '{}'", i + 1, line))?;
+        let [mass, x, y, vx, vy] = row[..] else {
+            return Err(format!(
+                "line {}: expected 5 columns mass,x,y,vx,vy, got {}",
+                i + 1,
+                row.len()
+            ));
+        };
+        if mass <= 0.0 {
+            return Err(format!("line {}: mass must be positive", i + 1));
+        }
+        bodies.push(new_sun(
+            bodies.len() as u32 + 1,
+            Point2::new(x, y) * units.length,
+            Vector2::new(vx, vy) * units.velocity,
+            mass * units.mass,
+            profile,
+        ));
+    }
+    Ok(bodies)
+}
+
+/// Read a star catalog, see `parse_catalog`.
+pub fn load_catalog(
+    path: &Path,
+    units: &CatalogUnits,
+    profile: &PhysicsProfile,
+) -> io::Result<Vec<Actor>> {
+    let csv = fs::read_to_string(path)?;
+    let bodies = parse_catalog(&csv, units, profile).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: {}", path.display(), e),
+        )
+    })?;
+    info!("loaded {} bodies from {}", bodies.len(), path.display());
+    Ok(bodies)
+}
+
+const TRAJECTORY_HEADER: [&str; 8] = ["time", "id", "kind", "mass", "x", "y", "vx", "vy"];
+
+/// Records where every body is every `interval` of simulated time, as CSV
+/// rows of `time,id,kind,mass,x,y,vx,vy` which `Trajectory` plays back. The
+/// kind is `sun` or `black_hole`.
+pub struct TrajectoryWriter<W: Write> {
+    writer: W,
+    interval: f32,
+    next: f32,
+}
+
+impl<W: Write> TrajectoryWriter<W> {
+    /// Start a trajectory with its header, the first sample is due right away.
+    pub fn new(mut writer: W, interval: f32) -> io::Result<TrajectoryWriter<W>> {
+        writeln!(writer, "{}", TRAJECTORY_HEADER.join(","))?;
+        Ok(TrajectoryWriter {
+            writer,
+            interval,
+            next: 0.0,
+        })
+    }
+
+    /// Add a sample of `sim` if one is due, returns whether it did.
+    pub fn update(&mut self, sim: &Simulation) -> io::Result<bool> {
+        let time = sim.time();
+        if time < self.next {
+            return Ok(false);
+        }
+        self.next = ((time / self.interval).floor() + 1.0) * self.interval;
+        self.sample(sim)?;
+        Ok(true)
+    }
+
+    /// Add a sample of `sim` whether one is due or not, like the end of a run.
+    pub fn sample(&mut self, sim: &Simulation) -> io::Result<()> {
+        for a in sim.actors() {
+            let kind = match a.tag {
+                ActorType::Sun => "sun",
+                ActorType::BlackHole => "black_hole",
+            };
+            let v = a.velocity();
+            writeln!(
+                self.writer,
+                "{},{},{},{},{},{},{},{}",
+                sim.time(),
+                a.id,
+                kind,
+                a.mass(),
+                a.pos.x,
+                a.pos.y,
+                v.x,
+                v.y
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Flush the rows and hand back the writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// A run recorded by `TrajectoryWriter`, the bodies as they were at every
+/// sampled time.
+#[derive(Debug, Clone)]
+pub struct Trajectory {
+    // sorted by time, the bodies of each sample by id
+    samples: Vec<(f32, Vec<Actor>)>,
+}
+
+impl Trajectory {
+    /// Samples from CSV rows of `time,id,kind,mass,x,y,vx,vy`, rows of the
+    /// same time make up one sample. Suns get their size and color from
+    /// `profile`, like those of a star catalog.
+    ///
+    /// Empty lines, `#` comments and a header naming the columns are skipped.
+    /// Any other row which is not eight columns with a known kind and
+    /// finite numbers, a positive mass and a time no earlier than the row
+    /// before, is an error naming its line.
+    pub fn parse(csv: &str, profile: &PhysicsProfile) -> Result<Trajectory, String> {
+        let mut samples: Vec<(f32, Vec<Actor>)> = Vec::new();
+        for (i, line) in csv.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            if samples.is_empty() && fields == TRAJECTORY_HEADER {
+                continue;
+            }
+            let [time, id, kind, numbers @ ..] = &fields[..] else {
+                return Err(format!(
+                    "line {}: expected 8 columns time,id,kind,mass,x,y,vx,vy, got {}",
+                    i + 1,
+                    fields.len()
+                ));
+            };
+            let id: u32 = id
+                .parse()
+                .map_err(|_| format!("line {}: not an id in '{}'", i + 1, line))?;
+            let row: Vec<f32> = [time]
+                .into_iter()
+                .chain(numbers)
+                .map(|f| f.parse::<f32>().ok().filter(|v| v.is_finite()))
+                .collect::<Option<_>>()
+                .ok_or_else(|| format!("line {}: not a number in '{}'", i + 1, line))?;
+            let [time, mass, x, y, vx, vy] = row[..] else {
+                return Err(format!(
+                    "line {}: expected 8 columns time,id,kind,mass,x,y,vx,vy, got {}",
+                    i + 1,
+                    fields.len()
+                ));
+            };
+            if mass <= 0.0 {
+                return Err(format!("line {}: mass must be positive", i + 1));
+            }
+            let (pos, velocity) = (Point2::new(x, y), Vector2::new(vx, vy));
+            let body = match *kind {
+                "sun" => new_sun(id, pos, velocity, mass, profile),
+                "black_hole" => new_black_hole(id, pos, velocity, mass),
+                _ => return Err(format!("line {}: unknown kind '{}'", i + 1, kind)),
+            };
+            match samples.last_mut() {
+                Some((t, bodies)) if *t == time => bodies.push(body),
+                Some((t, _)) if *t > time => {
+                    return Err(format!(
+                        "line {}: time must not be before the line before",
+                        i + 1
+                    ))
+                }
+                _ => samples.push((time, vec![body])),
+            }
+        }
+        if samples.is_empty() {
+            return Err("no samples".to_string());
+        }
+        for (_, bodies) in &mut samples {
+            bodies.sort_by_key(|b| b.id);
+        }
+        Ok(Trajectory { samples })
+    }
+
+    /// Read a trajectory, see `parse`.
+    pub fn load(path: &Path, profile: &PhysicsProfile) -> io::Result<Trajectory> {
+        let csv = fs::read_to_string(path)?;
+        let trajectory = Trajectory::parse(&csv, profile).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: {}", path.display(), e),
+            )
+        })?;
+        info!(
+            "loaded {} samples from {}",
+            trajectory.samples.len(),
+            path.display()
+        );
+        Ok(trajectory)
+    }
+
+    /// Time of the first sample.
+    pub fn start(&self) -> f32 {
+        self.samples[0].0
+    }
+
+    /// Time of the last sample.
+    pub fn end(&self) -> f32 {
+        self.samples[self.samples.len() - 1].0
+    }
+
+    /// The bodies at `time`, held at the first sample before it and at the
+    /// last one after it. In between, positions and velocities are
+    /// interpolated linearly, bodies which are gone by the next sample stay
+    /// where they were last seen.
+    pub fn bodies_at(&self, time: f32) -> Vec<Actor> {
+        let next = self.samples.partition_point(|(t, _)| *t <= time);
+        let Some((t0, from)) = next.checked_sub(1).map(|i| &self.samples[i]) else {
+            return self.samples[0].1.clone();
+        };
+        let Some((t1, to)) = self.samples.get(next) else {
+            return from.clone();
+        };
+        let s = (time - t0) / (t1 - t0);
+        let mut bodies = from.clone();
+        for b in &mut bodies {
+            if let Ok(j) = to.binary_search_by_key(&b.id, |a| a.id) {
+                b.pos = b.pos.lerp(to[j].pos, s);
+                *b.velocity_mut() = b.velocity().lerp(to[j].velocity(), s);
+            }
+        }
+        bodies
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::galaxy::{create_black_holes, create_suns, SimConfig};
+    use assert_approx_eq::assert_approx_eq;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_json_output_parses() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let suns = create_suns(4, 300.0, &PhysicsProfile::ARCADE, true, &mut rng);
+        let mut sim = Simulation::new(suns, SimConfig::default());
+        sim.step(1.0 / 60.0);
+        let path = std::env::temp_dir().join(format!("nbody-output-{}.json", std::process::id()));
+        write_json(&sim, None, &path).unwrap();
+        let json: serde_json::Value = serde_json::from_reader(File::open(&path).unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(json["bodies"].as_array().unwrap().len(), sim.actors().len());
+        assert_eq!(json["diagnostics"]["steps"], 1);
+    }
+
+    #[test]
+    fn test_checkpoint_round_trip() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let suns = create_suns(4, 300.0, &PhysicsProfile::ARCADE, true, &mut rng);
+        let mut sim = Simulation::new(suns, SimConfig::default());
+        let dir = std::env::temp_dir().join(format!("nbody-checkpoints-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let mut checkpoints = Checkpoints::new(dir.clone(), 0.5, 2);
+        let camera = CameraState::default();
+        let mut written = 0;
+        while written < 3 {
+            sim.step(1.0 / 60.0);
+            checkpoints.wait().unwrap();
+            if checkpoints.update(&sim, Some(&camera)).unwrap() {
+                written += 1;
+            }
+        }
+        checkpoints.wait().unwrap();
+        assert_approx_eq!(sim.time(), 1.5, 0.02);
+        assert!(!checkpoints.path(2).exists());
+        let older = read_scenario(&checkpoints.path(1)).unwrap();
+        let latest = read_scenario(&checkpoints.path(0)).unwrap();
+        assert_eq!(latest.camera, camera);
+        assert_eq!(latest.bodies.len(), sim.actors().len());
+        for (loaded, body) in latest.bodies.iter().zip(sim.actors()) {
+            assert_eq!(loaded.id, body.id);
+            assert_eq!(loaded.pos, body.pos);
+            assert_eq!(loaded.velocity(), body.velocity());
+            assert_eq!(loaded.mass(), body.mass());
+        }
+        assert_ne!(older.bodies[0].pos, latest.bodies[0].pos);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_failed_write_keeps_old_file() {
+        let path = std::env::temp_dir().join(format!("nbody-atomic-{}.json", std::process::id()));
+        fs::write(&path, "old").unwrap();
+        let failed = write_atomically(&path, |w| {
+            w.write_all(b"half")?;
+            Err(io::Error::other("crash"))
+        });
+        assert!(failed.is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "old");
+        write_atomically(&path, |w| w.write_all(b"new")).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+        let mut tmp = path.clone().into_os_string();
+        tmp.push(".tmp");
+        assert!(!PathBuf::from(tmp).exists());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_camera_state_round_trip() {
+        let camera = CameraState {
+            center: Point2::new(-120.5, 42.0),
+            zoom: 0.25,
+            speed: 4.0,
+            running: false,
+            show_traces: false,
+        };
+        let json = serde_json::to_string(&camera).unwrap();
+        assert_eq!(serde_json::from_str::<CameraState>(&json).unwrap(), camera);
+    }
+
+    #[test]
+    fn test_catalog_conversion() {
+        let csv = "mass, x, y, vx, vy\n\
+                   # a binary and a far away companion\n\
+                   2.0, 1.0, 0.0, 0.0, 0.5\n\
+                   \n\
+                   2.0, -1.0, 0.0, 0.0, -0.5\n\
+                   0.5, 0.0, 40.0, 1.0, 0.0\n";
+        let units = CatalogUnits {
+            mass: 1_000.0,
+            length: 10.0,
+            velocity: 4.0,
+        };
+        let bodies = parse_catalog(csv, &units, &PhysicsProfile::ARCADE).unwrap();
+        assert_eq!(bodies.len(), 3);
+        assert_eq!(bodies[0].mass(), 2_000.0);
+        assert_eq!(bodies[1].pos, Point2::new(-10.0, 0.0));
+        assert_eq!(bodies[1].velocity(), Vector2::new(0.0, -2.0));
+        assert_eq!(bodies[2].mass(), 500.0);
+        assert_eq!(bodies[2].pos, Point2::new(0.0, 400.0));
+        assert!(bodies[2].radius < bodies[0].radius);
+        let ids: Vec<u32> = bodies.iter().map(|b| b.id).collect();
+        assert_eq!(ids, [1, 2, 3]);
+    }
+
+    #[test]
+    fn test_catalog_rejects_malformed_rows() {
+        let profile = PhysicsProfile::ARCADE;
+        let parse = |csv| parse_catalog(csv, &CatalogUnits::default(), &profile);
+        assert!(parse("1, 2, 3, 4").unwrap_err().starts_with("line 1:"));
+        assert!(parse("1, 0, 0, 0, 0\n1, x, 0, 0, 0")
+            .unwrap_err()
+            .starts_with("line 2:"));
+        assert!(parse("0, 0, 0, 0, 0").is_err());
+        assert!(parse("1, 0, NaN, 0, 0").is_err());
+    }
+
+    #[test]
+    fn test_trajectory_round_trip() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut bodies = create_suns(4, 300.0, &PhysicsProfile::ARCADE, true, &mut rng);
+        bodies.extend(create_black_holes(1, 300.0, &mut rng));
+        let mut sim = Simulation::new(bodies, SimConfig::default());
+        let mut writer = TrajectoryWriter::new(Vec::new(), 0.25).unwrap();
+        let mut recorded = Vec::new();
+        for _ in 0..60 {
+            if writer.update(&sim).unwrap() {
+                recorded.push((sim.time(), sim.actors().to_vec()));
+            }
+            sim.step(1.0 / 60.0);
+        }
+        let csv = String::from_utf8(writer.finish().unwrap()).unwrap();
+        let trajectory = Trajectory::parse(&csv, &PhysicsProfile::ARCADE).unwrap();
+        assert_eq!(recorded.len(), 4);
+        assert_eq!(trajectory.start(), 0.0);
+        assert_eq!(trajectory.end(), recorded[3].0);
+        for (time, actors) in &recorded {
+            let replayed = trajectory.bodies_at(*time);
+            assert_eq!(replayed.len(), actors.len());
+            for body in actors {
+                let loaded = replayed.iter().find(|b| b.id == body.id).unwrap();
+                assert_eq!(loaded.tag, body.tag);
+                assert_eq!(loaded.pos, body.pos);
+                assert_eq!(loaded.velocity(), body.velocity());
+                assert_eq!(loaded.mass(), body.mass());
+                assert_approx_eq!(loaded.radius, body.radius, 1e-4);
+            }
+        }
+        let (t0, t1) = (recorded[1].0, recorded[2].0);
+        let halfway = trajectory.bodies_at((t0 + t1) / 2.0);
+        let id = recorded[1].1[0].id;
+        let body = halfway.iter().find(|b| b.id == id).unwrap();
+        let (from, to) = (recorded[1].1[0].pos, recorded[2].1[0].pos);
+        assert!(body.pos.distance(from.lerp(to, 0.5)) < 1e-3);
+    }
+
+    #[test]
+    fn test_trajectory_rejects_malformed_rows() {
+        let profile = PhysicsProfile::ARCADE;
+        let parse = |csv| Trajectory::parse(csv, &profile).map(|_| ());
+        assert!(parse("0, 1, sun, 1, 0, 0, 0")
+            .unwrap_err()
+            .starts_with("line 1:"));
+        assert!(parse("0, 1, comet, 1, 0, 0, 0, 0").is_err());
+        assert!(parse("1, 1, sun, 1, 0, 0, 0, 0\n0, 1, sun, 1, 0, 0, 0, 0")
+            .unwrap_err()
+            .starts_with("line 2:"));
+        assert!(parse("# nothing recorded").is_err());
+    }
+
+    #[test]
+    fn test_scenario_without_camera_uses_default() {
+        let scenario: Scenario = serde_json::from_str(r#"{"bodies": []}"#).unwrap();
+        assert_eq!(scenario.camera, CameraState::default());
+    }
+}
@@ -1,185 +1,3643 @@
-use std::collections::VecDeque;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, VecDeque};
+use std::fs::{self, File};
+use std::hash::Hash;
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
 
 use ggez::conf;
 use ggez::event;
-use ggez::event::{EventHandler, KeyCode, KeyMods};
+use ggez::event::{EventHandler, KeyCode, KeyMods, MouseButton};
 use ggez::graphics;
-use ggez::graphics::DrawParam;
+use ggez::graphics::{DrawParam, Drawable};
+use ggez::input::keyboard;
 use ggez::timer;
 use ggez::{Context, ContextBuilder, GameResult};
+use glam::{Mat2, Mat4, Vec4};
+use log::{debug, error, info, warn};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::Deserialize;
 
-use super::galaxy::Actor;
-use super::galaxy::Point2;
-use super::galaxy::{create_suns, update_vel_and_pos};
+use super::sound::ImpactSound;
+use super::theme::{parse_hex_color, Theme};
+use nbody::animation::{CameraPath, GifRecorder};
+use nbody::export::{
+    load_catalog, read_scenario, write_json, CameraState, CatalogUnits, Checkpoints, Trajectory,
+    TrajectoryWriter,
+};
+use nbody::galaxy::{center_of_mass, closest_approach, create_ring, create_sun};
+use nbody::galaxy::{collision_courses, tidal_acceleration};
+use nbody::galaxy::{create_black_holes, create_suns, create_suns_by_class, zero_total_momentum};
+use nbody::galaxy::{dominant_neighbor, dominant_period, fastest, hottest, most_massive};
+use nbody::galaxy::{kinetic_energy, potential_at, potential_energy, predict_path};
+use nbody::galaxy::{velocity_dispersion, virial_ratio};
+use nbody::galaxy::{Actor, ActorType, CollisionEvent, CollisionKind, PhysicsProfile};
+use nbody::galaxy::{ClassCounts, SimConfig, Simulation};
+use nbody::galaxy::{Point2, Slingshot, SlingshotDetector, Vector2};
+use nbody::svg::write_svg;
+use nbody::units::UnitSystem;
 
 const SCREEN_W: f32 = 1200.0;
 const SCREEN_H: f32 = 800.0;
 
 const ZOOM_FACTOR: f32 = 1.2;
 const SPEED_FACTOR: f32 = 2.0;
+const ROTATE_STEP: f32 = std::f32::consts::PI / 12.0;
+const TILT_DEPTH_OFFSET: f32 = 0.15; // share of its pseudo-depth a tilted point sinks towards the middle
 const MOVE_DELTA: f32 = SCREEN_W / 10.0;
+const NUDGE_DELTA: f32 = 5.0; // pixels per second on screen, whatever the zoom
+const MASS_PAINT_RATE: f32 = 2.0; // factor per second the mass changes by while painting
+const MASS_PAINT_MIN: f32 = 0.01; // painting never shrinks a body below this mass
+
+const COLD_VELOCITY_SHARE: f32 = 0.01; // of the usual starting speed left in a cold galaxy
+const MAX_FRAME_LAG: Duration = Duration::from_millis(250); // physics time dropped beyond this
+
+const ACCRETION_RING_COLOR: u32 = 0xffb0_40ff;
+
+const FLASH_COLOR: u32 = 0xffff_e0ff;
+const FLASH_LIFETIME: f32 = 0.6; // seconds
+const FLASH_MAX_RADIUS: f32 = 40.0; // pixels
+const FLASH_ENERGY_SCALE: f32 = 15.0; // ln(1 + energy) giving full intensity
+const TRACE_FADE_TIME: f32 = 0.8; // seconds the trace of a merged body lingers
+const SHAKE_MAX: f32 = 12.0; // pixels the camera moves for the hardest hits
+const SHAKE_MIN_INTENSITY: f32 = 0.5; // flash intensity of the softest hit that shakes
+const SHAKE_DECAY: f32 = 0.85; // share of the shake left after a frame
+const SHAKE_STOP: f32 = 0.1; // pixels below which the shake is over
+
+const CIRCLE_TOLERANCE: f32 = 0.1; // pixels a body circle may stray from round at quality 1
+const MIN_CIRCLE_SEGMENTS: f32 = 8.0; // for the tiniest bodies far out
+const MAX_CIRCLE_SEGMENTS: f32 = 256.0; // for huge bodies up close
+
+const GLOW_LAYERS: usize = 8; // concentric circles making up a glow
+const GLOW_ALPHA: f32 = 0.08; // opacity of a single layer, the core stacks all of them
+const GLOW_BASE: f32 = 1.5; // glow radius of the lightest bodies in body radii
+const GLOW_MASS_SCALE: f32 = 0.3; // extra body radii of glow per e-fold of mass
+const STAR_SPRITE: &[u8] = include_bytes!("../resources/star.png"); // white, so it takes any tint
+const STAR_SPRITE_CORE: f32 = 16.0; // radius in pixels of the disc in the middle of the sprite
+
+const TRACE_COLOR: u32 = 0xffff_ff60; // default for traces of a fixed color
+const SLINGSHOT_RANGE: f32 = 10.0; // summed radii within which bodies are passing
+const ANNOTATION_LIFETIME: f32 = 2.5; // seconds
+const ANNOTATION_COLOR: u32 = 0xa0e0_ffff;
+const SELECTION_COLOR: u32 = 0xffff_ffff;
+const PINNED_COLOR: u32 = 0x60c0_ffff;
+const HEAVIEST_COLOR: u32 = 0xffa0_40ff;
+const FASTEST_COLOR: u32 = 0xff40_a0ff;
+const HOTTEST_COLOR: u32 = 0xffe0_40ff;
+const TIDE_COLOR: u32 = 0x80ff_c0ff;
+const TIDE_SCALE: f32 = 8.0; // pixels of arrow per e-fold of tidal acceleration
+const TIDE_HEAD: f32 = 5.0; // pixels
+const SELECTION_MARGIN: f32 = 5.0; // pixels around a body still selecting it
+const PREDICTION_COLOR: u32 = 0xffff_ff80;
+const PREDICTION_STEPS: usize = 600;
+const PREDICTION_REFRESH_STEPS: u64 = 30;
+const PREDICTION_DASH: usize = 8; // steps per dash and per gap
+
+const ID_COLOR: u32 = 0xc0c0_c0ff;
+const ID_TEXT_SIZE: f32 = 12.0; // pixels
+const ID_MIN_RADIUS: f32 = 3.0; // pixels, smaller bodies get no id label
+
+const BARYCENTER_LINE_COLOR: u32 = 0xffff_ffff;
+const BARYCENTER_LINE_ALPHA: f32 = 0.3; // of the heaviest body's line
+const BARYCENTER_LINE_MIN_ALPHA: f32 = 0.02; // fainter lines are left out
+const BARYCENTER_LINES_MAX: usize = 64; // only the heaviest bodies get a line
+const COURSE_HORIZON: f32 = 2.0; // simulated seconds ahead hits are shown
+const COURSE_LATE_COLOR: u32 = 0xffe0_4020; // for a hit right at the horizon
+const COURSE_SOON_COLOR: u32 = 0xff30_20a0; // for a hit about to happen
+
+const HUD_MARGIN: f32 = 10.0;
+const INSPECT_OFFSET: f32 = 16.0; // pixels between cursor and the nearest bodies panel
+const INSPECT_PADDING: f32 = 4.0;
+const INSPECT_BACKGROUND: u32 = 0x0000_00a0;
+
+const HELP_DIM: f32 = 0.85; // opacity of the background over the bodies behind the help
+const HELP_MARGIN: f32 = 20.0; // pixels
+const HELP_KEY_WIDTH: usize = 9; // characters, the hud font is monospaced
+const SPLIT_DIVIDER_COLOR: u32 = 0x8080_80ff;
+const SPLIT_LABEL_MARGIN: f32 = 40.0; // pixels from the bottom to the labels
+const PERF_HISTORY: usize = 120; // frames shown in the frame time graph
+const PERF_GRAPH_W: f32 = 240.0;
+const PERF_GRAPH_H: f32 = 60.0;
+const PERF_GRAPH_SCALE: f32 = 1.0 / 30.0; // frame time in seconds at the top of the graph
+const PERF_BAR_COLOR: u32 = 0x60e0_60c0;
+const PERF_BACKGROUND: u32 = 0x0000_00a0;
+const ENERGY_HISTORY: usize = 300; // samples shown in the energy plot
+const ENERGY_SAMPLE_STEPS: u64 = 10; // physics steps between two samples
+const ENERGY_GRAPH_W: f32 = 300.0;
+const ENERGY_GRAPH_H: f32 = 120.0;
+const ENERGY_LINE_COLOR: u32 = 0xffd0_60ff;
+const SLOWMO_RANGE: f32 = 4.0; // summed radii below which slow motion kicks in
+const SLOWMO_MIN: f32 = 0.1; // share of the speed left for touching bodies
+const SLOWMO_SMOOTH: f32 = 0.05; // how far the slow down moves to its target per frame
+const VIRIAL_SMOOTH: f32 = 0.05; // weight of the newest virial ratio per frame
+
+const POTENTIAL_REFRESH_FRAMES: u32 = 10; // frames a computed potential field is shown for
+const POTENTIAL_SHALLOW_COLOR: u32 = 0x5070_b050;
+const POTENTIAL_DEEP_COLOR: u32 = 0x0000_0890;
+
+const MINIMAP_SIZE: f32 = 160.0; // pixels, the minimap is square
+const MINIMAP_MARGIN: f32 = 10.0;
+const MINIMAP_PADDING: f32 = 0.1; // share of the minimap left empty around the bodies
+const MINIMAP_BACKGROUND: u32 = 0x0000_00a0;
+const MINIMAP_BORDER_COLOR: u32 = 0x8080_80ff;
+const MINIMAP_VIEWPORT_COLOR: u32 = 0xe0e0_e0ff;
+const MESH_CACHE_LIMIT: usize = 512; // meshes of one kind kept, beyond that they are built anew
+
+/// Meshes reused across frames instead of being rebuilt for every body.
+///
+/// Circles and glows are white, centered on the origin and keyed by their
+/// quantized on-screen radius; they get tinted and moved into place via
+/// `DrawParam`, so they stay valid whatever the zoom. Each kind is dropped
+/// once `MESH_CACHE_LIMIT` different sizes piled up.
+/// Traces are kept in world coordinates per body id, along with the zoom
+/// their line width was built for, and only rebuilt when a new point was
+/// sampled or the zoom changed.
+struct MeshCache {
+    /// See `circle_tolerance`.
+    quality: f32,
+    circles: HashMap<u32, graphics::Mesh>,
+    rings: HashMap<u32, graphics::Mesh>,
+    // keyed by body radius and glow radius
+    glows: HashMap<(u32, u32), graphics::Mesh>,
+    traces: HashMap<u32, (Point2, f32, graphics::Mesh)>,
+}
+
+impl MeshCache {
+    fn new(quality: f32) -> MeshCache {
+        MeshCache {
+            quality,
+            circles: HashMap::new(),
+            rings: HashMap::new(),
+            glows: HashMap::new(),
+            traces: HashMap::new(),
+        }
+    }
+}
+
+/// Entry for `key` in one kind of cached meshes, emptied first if it is full.
+fn bounded_entry<K: Hash + Eq>(
+    meshes: &mut HashMap<K, graphics::Mesh>,
+    key: K,
+) -> Entry<'_, K, graphics::Mesh> {
+    if meshes.len() >= MESH_CACHE_LIMIT && !meshes.contains_key(&key) {
+        meshes.clear();
+    }
+    meshes.entry(key)
+}
+
+/// Trace of a body which merged into another one or was swallowed, fading
+/// out instead of vanishing at once.
+struct FadingTrace {
+    points: Vec<Point2>,
+    color: u32,
+    lifetime: f32,
+}
+
+impl FadingTrace {
+    fn new(actor: &Actor, style: TraceStyle) -> FadingTrace {
+        FadingTrace {
+            points: actor.trace.iter().copied().collect(),
+            color: style.color(actor),
+            lifetime: TRACE_FADE_TIME,
+        }
+    }
+}
+
+/// Expanding, fading ring drawn where a collision happened.
+struct Flash {
+    pos: Point2,
+    intensity: f32,
+    lifetime: f32,
+}
+
+impl Flash {
+    fn new(event: &CollisionEvent) -> Flash {
+        Flash {
+            pos: event.pos,
+            intensity: (event.energy.ln_1p() / FLASH_ENERGY_SCALE).clamp(0.2, 1.0),
+            lifetime: FLASH_LIFETIME,
+        }
+    }
+}
+
+/// Jitter of the camera after a hard hit in view, fading out over a few
+/// frames.
+struct Shake {
+    /// Pixels the camera is moved by in the next frame.
+    amplitude: f32,
+    rng: StdRng,
+}
+
+impl Default for Shake {
+    fn default() -> Shake {
+        Shake {
+            amplitude: 0.0,
+            rng: StdRng::seed_from_u64(0),
+        }
+    }
+}
+
+impl Shake {
+    /// Shake for `event` if it happened on screen and hard enough.
+    fn hit(&mut self, event: &CollisionEvent, view: View) {
+        let screen = graphics::Rect::new(0.0, 0.0, view.screen_width, view.screen_height);
+        if !screen.contains(view.to_screen(event.pos)) {
+            return;
+        }
+        let intensity = event.energy.ln_1p() / FLASH_ENERGY_SCALE;
+        let strength = (intensity - SHAKE_MIN_INTENSITY) / (1.0 - SHAKE_MIN_INTENSITY);
+        let amplitude = SHAKE_MAX * strength.clamp(0.0, 1.0);
+        if amplitude > self.amplitude {
+            self.amplitude = amplitude;
+            let (a, b) = event.ids;
+            self.rng = StdRng::seed_from_u64((a as u64) << 32 | b as u64);
+        }
+    }
+
+    /// Offset in pixels to move the camera by this frame.
+    fn next_offset(&mut self) -> Vector2 {
+        if self.amplitude < SHAKE_STOP {
+            self.amplitude = 0.0;
+            return Vector2::ZERO;
+        }
+        let angle = self.rng.gen_range(0.0..std::f32::consts::TAU);
+        let offset = Vector2::new(angle.cos(), angle.sin()) * self.amplitude;
+        self.amplitude *= SHAKE_DECAY;
+        offset
+    }
+}
+
+/// Fading label where a body was slung by a close pass.
+struct Annotation {
+    pos: Point2,
+    text: String,
+    lifetime: f32,
+}
+
+impl Annotation {
+    fn new(slingshot: &Slingshot) -> Annotation {
+        let gain = slingshot.speed_after - slingshot.speed_before;
+        Annotation {
+            pos: slingshot.pos,
+            text: format!(
+                "slingshot +{:.0} ({:+.0}%)",
+                gain,
+                gain / slingshot.speed_before * 100.0
+            ),
+            lifetime: ANNOTATION_LIFETIME,
+        }
+    }
+}
+
+/// What the color of a body tells.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ColorMode {
+    /// Its star class.
+    Class,
+    /// Its speed compared to the fastest body, from blue to red.
+    Speed,
+}
+
+impl ColorMode {
+    /// The color of each of `actors`.
+    fn painter(self, actors: &[Actor]) -> impl Fn(&Actor) -> u32 {
+        let max_speed = fastest(actors).map_or(0.0, |idx| actors[idx].velocity().length());
+        move |s: &Actor| match self {
+            ColorMode::Class => s.color,
+            ColorMode::Speed => speed_color(s.velocity().length(), max_speed),
+        }
+    }
+}
+
+/// Share of the speed to run at in slow motion when the closest two bodies are
+/// `separation` summed radii apart, falling linearly from 1 at `SLOWMO_RANGE`
+/// to `SLOWMO_MIN` when touching.
+fn slowmo_target(separation: Option<f32>) -> f32 {
+    match separation {
+        Some(s) => {
+            let t = ((s - 1.0) / (SLOWMO_RANGE - 1.0)).clamp(0.0, 1.0);
+            SLOWMO_MIN + (1.0 - SLOWMO_MIN) * t
+        }
+        None => 1.0,
+    }
+}
+
+/// Steps between two trace points at `speed`, so the points stay as far apart
+/// in space as `interval` steps at normal speed leave them.
+fn scaled_trace_interval(interval: u32, speed: f32) -> u32 {
+    (interval as f32 / speed).round().max(1.0) as u32
+}
+
+/// Fully saturated opaque color of `hue` in sixths of the color wheel,
+/// 0 is red, 2 green and 4 blue.
+fn hue_color(hue: f32) -> u32 {
+    let rising = |x: f32| (x.clamp(0.0, 1.0) * 255.0).round() as u32;
+    let r = rising((hue - 3.0).abs() - 1.0);
+    let g = rising(2.0 - (hue - 2.0).abs());
+    let b = rising(2.0 - (hue - 4.0).abs());
+    r << 24 | g << 16 | b << 8 | 0xff
+}
+
+/// Blue for standing still, over green and yellow to red for `max_speed`.
+fn speed_color(speed: f32, max_speed: f32) -> u32 {
+    let heat = if max_speed > 0.0 {
+        (speed / max_speed).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    // walk the hue from blue (240 degrees) down to red (0 degrees)
+    hue_color((1.0 - heat) * 4.0)
+}
+
+/// Which color the traces are drawn in.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum TraceStyle {
+    /// The color of the body.
+    MatchBody,
+    /// A hue picked by body id, so it never changes.
+    Rainbow,
+    /// The same RGBA color for all bodies.
+    Fixed(u32),
+}
+
+impl TraceStyle {
+    fn color(self, actor: &Actor) -> u32 {
+        match self {
+            TraceStyle::MatchBody => actor.color,
+            TraceStyle::Rainbow => {
+                // Fibonacci hashing spreads neighboring ids around the wheel.
+                let spread = actor.id.wrapping_mul(0x9e37_79b9) >> 16;
+                hue_color(spread as f32 / 65_536.0 * 6.0)
+            }
+            TraceStyle::Fixed(color) => color,
+        }
+    }
+
+    /// The style after this one when cycling through them, `fixed` being the
+    /// color to use for `Fixed`.
+    fn next(self, fixed: u32) -> TraceStyle {
+        match self {
+            TraceStyle::MatchBody => TraceStyle::Rainbow,
+            TraceStyle::Rainbow => TraceStyle::Fixed(fixed),
+            TraceStyle::Fixed(_) => TraceStyle::MatchBody,
+        }
+    }
+}
+
+impl std::str::FromStr for TraceStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "body" => Ok(TraceStyle::MatchBody),
+            "rainbow" => Ok(TraceStyle::Rainbow),
+            _ => parse_hex_color(s).map(TraceStyle::Fixed).map_err(|_| {
+                format!(
+                    "unknown trace style '{}', expected body, rainbow or a color like ffffff60",
+                    s
+                )
+            }),
+        }
+    }
+}
+
+impl TryFrom<String> for TraceStyle {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+/// Bodies worth keeping an eye on, which one that is changes over time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Extreme {
+    Heaviest,
+    Fastest,
+    // highest peak speed, which usually came from a close slingshot
+    Hottest,
+}
+
+impl Extreme {
+    fn find(self, actors: &[Actor]) -> Option<usize> {
+        match self {
+            Extreme::Heaviest => most_massive(actors),
+            Extreme::Fastest => fastest(actors),
+            Extreme::Hottest => hottest(actors),
+        }
+    }
+
+    fn color(self) -> u32 {
+        match self {
+            Extreme::Heaviest => HEAVIEST_COLOR,
+            Extreme::Fastest => FASTEST_COLOR,
+            Extreme::Hottest => HOTTEST_COLOR,
+        }
+    }
+}
+
+/// A second run of the same galaxy under other physics, for a split screen.
+#[derive(Debug, Clone)]
+pub struct Comparison {
+    /// Shown over its half of the screen.
+    pub label: String,
+    pub config: SimConfig,
+}
+
+/// Everything the game needs to know at start up besides the physics.
+pub struct Settings {
+    pub suns: u32,
+    /// Suns per star class, replaces `suns` if given.
+    pub class_counts: Option<ClassCounts>,
+    pub black_holes: u32,
+    pub seed: u64,
+    /// Physics steps per second.
+    pub fps: u32,
+    pub vsync: bool,
+    /// Start with the simulation stopped.
+    pub paused: bool,
+    /// Where to write the final state as JSON on exit.
+    pub output: Option<PathBuf>,
+    /// Where to write the traces as SVG, on exit and with F5.
+    pub svg: Option<PathBuf>,
+    /// Where to record the trajectory of a run without window as CSV.
+    pub trajectory: Option<PathBuf>,
+    /// Simulated seconds between two samples of the trajectory.
+    pub trajectory_interval: f32,
+    /// Trajectory to play back instead of simulating.
+    pub replay: Option<PathBuf>,
+    /// Start the replay over at its end instead of stopping.
+    pub replay_loop: bool,
+    /// Run shown in the right half of a split screen.
+    pub compare: Option<Comparison>,
+    /// Simulated seconds between two checkpoints, none if not given.
+    pub checkpoint_interval: Option<f32>,
+    /// Number of checkpoints kept on disk.
+    pub checkpoint_keep: usize,
+    pub checkpoint_dir: PathBuf,
+    /// Scenario to start from instead of a random galaxy.
+    pub load: Option<PathBuf>,
+    /// CSV star catalog to start from instead of a random galaxy.
+    pub catalog: Option<PathBuf>,
+    pub catalog_units: CatalogUnits,
+    /// Physical units the hud shows masses, lengths and times in.
+    pub units: Option<UnitSystem>,
+    /// Wall-clock seconds one orbit of the heaviest pair should take.
+    pub period_seconds: Option<f32>,
+    /// Number of bodies listed next to the cursor when inspecting.
+    pub inspect_count: usize,
+    /// Cancel the drift of a new galaxy so its center of mass stays put.
+    pub recenter_momentum: bool,
+    /// Pin the heaviest body of every new galaxy in place.
+    pub pin_heaviest: bool,
+    /// Start a new galaxy nearly at rest, later suns move as usual.
+    pub cold: bool,
+    /// Background, hud and trace colors, with `--background` and
+    /// `--trace-style` applied.
+    pub theme: Theme,
+    /// Number of suns, radius and central mass of rings spawned with n.
+    pub ring_count: u32,
+    pub ring_radius: f32,
+    pub ring_mass: f32,
+    /// Suns per second added in the stress test mode, off if `None`.
+    pub spawn_rate: Option<f32>,
+    /// Body count at which the stress test mode stops spawning.
+    pub max_bodies: u32,
+    /// Draw a soft glow around suns.
+    pub glow: bool,
+    /// Draw suns as tinted star sprites instead of meshes.
+    pub sprites: bool,
+    /// Play a sound on collisions.
+    pub sound: bool,
+    /// Shake the camera on hard hits in view.
+    pub shake: bool,
+    /// Draw the gravitational potential under the bodies, in a grid this
+    /// many cells wide.
+    pub potential_field: bool,
+    pub potential_grid: u32,
+    /// Scales how finely body circles are tessellated.
+    pub circle_quality: f32,
+    /// Degrees the plane is leaned back on screen, only for the looks.
+    pub tilt: f32,
+    /// Share of the way to its target the zoom moves per frame.
+    pub zoom_smoothing: f32,
+    /// Share of the way to its target the camera pans per frame.
+    pub pan_smoothing: f32,
+    /// Steps between two trace points at normal speed.
+    pub trace_interval: u32,
+    /// Trace points shared by all bodies, unlimited if `None`.
+    pub trace_budget: Option<usize>,
+    /// Share of its speed a body has to gain in a close pass to be
+    /// annotated as slingshot.
+    pub slingshot_threshold: f32,
+    /// Radius new galaxies are spawned in, by default it grows with the
+    /// number of suns.
+    pub galaxy_radius: Option<f32>,
+    /// Where to write an animated GIF of a headless run.
+    pub gif: Option<PathBuf>,
+    pub gif_frames: u32,
+    /// The GIF is this many times smaller than the window.
+    pub gif_scale: u32,
+    /// Keyframes for the camera to fly through, in the window and the GIF.
+    pub camera_path: Option<PathBuf>,
+    /// Print a line per collision and ejection to stdout.
+    pub events: bool,
+}
+
+/// Decides how many fixed size physics steps are due, independent of how
+/// often frames get drawn.
+struct FixedTimestep {
+    step: Duration,
+    residual: Duration,
+}
+
+impl FixedTimestep {
+    fn new(fps: u32) -> FixedTimestep {
+        FixedTimestep {
+            step: Duration::from_secs(1) / fps,
+            residual: Duration::ZERO,
+        }
+    }
+
+    /// Account for `elapsed` wall time and return the number of steps due.
+    /// When falling behind by more than `MAX_FRAME_LAG`, the rest is dropped
+    /// instead of trying to catch up.
+    fn advance(&mut self, elapsed: Duration) -> u32 {
+        self.residual = (self.residual + elapsed).min(MAX_FRAME_LAG);
+        let steps = (self.residual.as_nanos() / self.step.as_nanos()) as u32;
+        self.residual -= self.step * steps;
+        steps
+    }
+}
+
+/// Stress test mode adding suns at a steady rate until `max_bodies` are in.
+struct ContinuousSpawn {
+    per_step: f32,
+    due: f32,
+    max_bodies: u32,
+    capped: bool,
+}
+
+impl ContinuousSpawn {
+    fn new(rate: f32, fps: u32, max_bodies: u32) -> ContinuousSpawn {
+        ContinuousSpawn {
+            per_step: rate / fps as f32,
+            due: 0.0,
+            max_bodies,
+            capped: false,
+        }
+    }
+
+    /// Account for one physics step with `bodies` around and return the
+    /// number of suns to spawn now. Nothing piles up while at the cap, so
+    /// there is no burst when bodies merge and make room again.
+    fn advance(&mut self, bodies: usize) -> u32 {
+        let room = (self.max_bodies as usize).saturating_sub(bodies) as u32;
+        if room == 0 {
+            if !self.capped {
+                info!("reached {} bodies, spawning paused", self.max_bodies);
+                self.capped = true;
+            }
+            self.due = 0.0;
+            return 0;
+        }
+        self.capped = false;
+        self.due += self.per_step;
+        let spawn = self.due.floor();
+        self.due -= spawn;
+        (spawn as u32).min(room)
+    }
+}
 
 struct MainState {
-    suns: Vec<Actor>,
+    sim: Simulation,
+    rng: StdRng,
+    settings: Settings,
+    timestep: FixedTimestep,
+    meshes: MeshCache,
+    star_sprite: Option<graphics::Image>,
+    potential: PotentialField,
+    shake: Shake,
+    flashes: Vec<Flash>,
+    fading_traces: Vec<FadingTrace>,
+    spawner: Option<ContinuousSpawn>,
+    sound: Option<ImpactSound>,
+    checkpoints: Option<Checkpoints>,
+    slingshots: SlingshotDetector,
+    annotations: Vec<Annotation>,
+    selected: Option<u32>,
+    prediction: Vec<Point2>,
+    // selected id, step and dt the prediction was computed for
+    prediction_for: Option<(u32, u64, f32)>,
     screen_width: f32,
     screen_height: f32,
     center: Point2,
     center_target: Point2,
     zoom: f32,
     zoom_target: f32,
+    // counterclockwise turn of the world on screen in radians
+    view_angle: f32,
     speed: f32,
     running: bool,
     show_traces: bool,
+    color_mode: ColorMode,
+    theme: Theme,
+    trace_style: TraceStyle,
+    // the color of `TraceStyle::Fixed` when cycling through the styles
+    trace_color: u32,
+    // steps between trace points at speed 1, scaled with the actual speed
+    trace_interval: u32,
+    show_hud: bool,
+    show_perf: bool,
+    show_ids: bool,
+    show_tides: bool,
+    show_barycenter_lines: bool,
+    show_courses: bool,
+    show_inspect: bool,
+    // slow down automatically when bodies are about to collide
+    cinematic: bool,
+    // factor on `speed` applied by the cinematic mode, 1 is no slow down
+    slowmo: f32,
+    show_energy: bool,
+    // (simulated time, total energy) sampled every `ENERGY_SAMPLE_STEPS`
+    energy: VecDeque<(f32, f32)>,
+    // durations of the last `PERF_HISTORY` frames in seconds
+    frame_times: VecDeque<f32>,
+    // virial ratio averaged over recent frames
+    virial: Option<f32>,
+    shut_down: bool,
+    show_minimap: bool,
+    show_heaviest: bool,
+    show_fastest: bool,
+    show_hottest: bool,
+    follow: Option<Extreme>,
+    // while it lasts, the camera follows it instead of keys and mouse
+    camera_path: Option<CameraPath>,
+    // played back instead of stepping the simulation
+    replay: Option<Trajectory>,
+    lock_barycenter: bool,
+    // where the view is centered relative to the barycenter while locked
+    barycenter_offset: Point2,
+    show_help: bool,
+    split: Option<Split>,
+}
+
+/// The run compared to the main one, drawn into the right half of the
+/// window while the main one takes the left half. Both start from the same
+/// bodies and step with the same time steps.
+struct Split {
+    label: String,
+    sim: Simulation,
+    meshes: MeshCache,
+    /// Total energy of the main and the compared run at their start.
+    start_energy: (f32, f32),
+    left: graphics::Canvas,
+    right: graphics::Canvas,
+}
+
+impl Split {
+    /// Compare `main`, which has not moved yet, to a copy of it running
+    /// under the physics of `comparison`.
+    fn new(
+        ctx: &mut Context,
+        comparison: &Comparison,
+        main: &Simulation,
+        settings: &Settings,
+    ) -> GameResult<Split> {
+        let sim = simulation(main.actors().to_vec(), comparison.config.clone(), settings);
+        let (left, right) = half_canvases(ctx)?;
+        Ok(Split {
+            label: comparison.label.clone(),
+            start_energy: (total_energy(main), total_energy(&sim)),
+            sim,
+            meshes: MeshCache::new(settings.circle_quality),
+            left,
+            right,
+        })
+    }
+
+    /// Start both runs over from `main`, e.g. after a reset.
+    fn restart(&mut self, main: &Simulation, settings: &Settings) {
+        let config = self.sim.config().clone();
+        self.sim = simulation(main.actors().to_vec(), config, settings);
+        self.sim.set_sample_traces(main.samples_traces());
+        self.meshes = MeshCache::new(settings.circle_quality);
+        self.start_energy = (total_energy(main), total_energy(&self.sim));
+    }
+}
+
+/// Canvases for the two halves of the window, side by side.
+fn half_canvases(ctx: &mut Context) -> GameResult<(graphics::Canvas, graphics::Canvas)> {
+    let (width, height) = graphics::drawable_size(ctx);
+    let (width, height) = ((width / 2.0).max(1.0) as u16, height.max(1.0) as u16);
+    let format = graphics::get_window_color_format(ctx);
+    let mut halves =
+        [(); 2].map(|_| graphics::Canvas::new(ctx, width, height, conf::NumSamples::One, format));
+    for canvas in halves.iter_mut().flatten() {
+        // drawn with alpha blending, so its colors are premultiplied already
+        canvas.set_blend_mode(Some(graphics::BlendMode::Premultiplied));
+    }
+    let [left, right] = halves;
+    Ok((left?, right?))
+}
+
+/// Put the two halves of `split` next to each other on `screen`, each with
+/// its label and how far its energy drifted.
+fn draw_split(
+    ctx: &mut Context,
+    split: &Split,
+    main: &Simulation,
+    screen: graphics::Rect,
+) -> GameResult {
+    let half = screen.w / 2.0;
+    let size = DrawParam::default().scale(Point2::new(
+        half / split.left.width() as f32,
+        screen.h / split.left.height() as f32,
+    ));
+    graphics::draw(ctx, &split.left, size)?;
+    graphics::draw(ctx, &split.right, size.dest(Point2::new(half, 0.0)))?;
+    let divider = graphics::Mesh::new_line(
+        ctx,
+        &[Point2::new(half, 0.0), Point2::new(half, screen.h)],
+        1.0,
+        graphics::Color::from_rgba_u32(SPLIT_DIVIDER_COLOR),
+    )?;
+    graphics::draw(ctx, &divider, DrawParam::default())?;
+    let halves = [
+        ("base", main, split.start_energy.0, 0.0),
+        (split.label.as_str(), &split.sim, split.start_energy.1, half),
+    ];
+    for (label, sim, start, x) in halves {
+        let text = graphics::Text::new(format!(
+            "{}\nenergy drift {:+.3}%",
+            label,
+            100.0 * energy_drift(sim, start)
+        ));
+        let width = text.dimensions(ctx).w;
+        let dest = Point2::new(x + (half - width) / 2.0, screen.h - SPLIT_LABEL_MARGIN);
+        graphics::draw(ctx, &text, DrawParam::default().dest(dest))?;
+    }
+    Ok(())
+}
+
+fn total_energy(sim: &Simulation) -> f32 {
+    kinetic_energy(sim.actors()) + potential_energy(sim.actors(), sim.config())
+}
+
+/// How far the total energy of `sim` moved away from `start`, relative to it.
+fn energy_drift(sim: &Simulation, start: f32) -> f32 {
+    if start == 0.0 {
+        return 0.0;
+    }
+    (total_energy(sim) - start) / start.abs()
 }
 
-pub fn start(suns: u32) -> GameResult {
-    let cb = ContextBuilder::new("nbody", "wullewutz")
-        .window_setup(conf::WindowSetup::default().title("nbody!"))
-        .window_mode(conf::WindowMode::default().dimensions(SCREEN_W, SCREEN_H));
+/// A row of the help overlay and of the keybindings table in the README.
+struct KeyBinding {
+    keys: &'static str,
+    action: &'static str,
+    /// Whether what the keys toggle is on right now.
+    state: Option<fn(&MainState) -> bool>,
+}
+
+const fn key(keys: &'static str, action: &'static str) -> KeyBinding {
+    KeyBinding {
+        keys,
+        action,
+        state: None,
+    }
+}
+
+const fn toggle(
+    keys: &'static str,
+    action: &'static str,
+    state: fn(&MainState) -> bool,
+) -> KeyBinding {
+    KeyBinding {
+        keys,
+        action,
+        state: Some(state),
+    }
+}
+
+/// All keybindings, in the order of the README, which a test keeps in sync.
+/// `key_down_event` handles them.
+const KEYBINDINGS: &[KeyBinding] = &[
+    key("w/s/a/d", "move up/down/left/right"),
+    toggle("Space", "pause/resume", |s| !s.running),
+    key("+/-", "faster/slower"),
+    toggle("z", "slow motion near hits", |s| s.cinematic),
+    key("i/o", "zoom in/out"),
+    key("q/e", "rotate view"),
+    toggle("c", "lock view to barycenter", |s| s.lock_barycenter),
+    toggle("t", "toggle body traces", |s| s.show_traces),
+    toggle("T", "freeze/resume traces", |s| !s.sim.samples_traces()),
+    key("[/]", "denser/sparser traces"),
+    key("l", "cycle trace colors"),
+    key("v", "color by class/speed"),
+    toggle("h", "toggle hud", |s| s.show_hud),
+    toggle("F4", "toggle energy plot", |s| s.show_energy),
+    toggle("F2", "toggle body ids", |s| s.show_ids),
+    toggle("F3", "toggle frame time graph", |s| s.show_perf),
+    key("F5", "save traces as SVG"),
+    toggle("m", "toggle minimap", |s| s.show_minimap),
+    key("r", "reset with a new galaxy"),
+    key("R", "restart the same galaxy"),
+    key("j/k", "add sun/remove lightest"),
+    key("n", "spawn ring at cursor"),
+    key("click", "select body, show path"),
+    key("p", "pin/unpin selected body"),
+    toggle("g", "show tides on selected", |s| s.show_tides),
+    toggle("x", "lines to barycenter", |s| s.show_barycenter_lines),
+    toggle("F6", "lines between near hits", |s| s.show_courses),
+    toggle("u", "list bodies near cursor", |s| s.show_inspect),
+    key("arrows", "nudge selected body"),
+    key("y", "snap selected to orbit"),
+    key(". / ,", "grow/shrink body (hold)"),
+    key("b/f", "mark heaviest/fastest"),
+    key("B/F", "follow heaviest/fastest"),
+    toggle("F7", "mark fastest ever", |s| s.show_hottest),
+    key("F1 / ?", "show keybindings"),
+    key("Esc", "quit"),
+];
 
-    let (mut ctx, events_loop) = cb.build()?;
-    let game = MainState::new(&mut ctx, suns)?;
+/// `binding` as a line of the help overlay, with its state if it has one.
+fn help_line(binding: &KeyBinding, on: Option<bool>) -> String {
+    let line = format!(
+        "{:<width$}{}",
+        binding.keys,
+        binding.action,
+        width = HELP_KEY_WIDTH
+    );
+    match on {
+        Some(true) => line + " [on]",
+        Some(false) => line + " [off]",
+        None => line,
+    }
+}
+
+pub fn start(mut settings: Settings, config: SimConfig) -> GameResult {
+    let builder = |audio| {
+        ContextBuilder::new("nbody", "wullewutz")
+            .window_setup(
+                conf::WindowSetup::default()
+                    .title("nbody!")
+                    .vsync(settings.vsync),
+            )
+            .window_mode(
+                conf::WindowMode::default()
+                    .dimensions(SCREEN_W, SCREEN_H)
+                    .resizable(true),
+            )
+            .modules(conf::ModuleConf::default().audio(audio))
+    };
+
+    let (mut ctx, events_loop) = match builder(settings.sound).build() {
+        // The audio device is set up before the window, so trying again
+        // without it is fine.
+        Err(ggez::GameError::AudioError(e)) => {
+            warn!("no collision sounds: {}", e);
+            settings.sound = false;
+            builder(false).build()?
+        }
+        result => result?,
+    };
+    let game = MainState::new(&mut ctx, settings, config)?;
     event::run(ctx, events_loop, game)
 }
 
+/// The world is turned counterclockwise by `angle` around `center` before
+/// it is scaled by `zoom` and put in the middle of the screen. A `tilt` in
+/// radians leans the plane back like a disk seen at an angle, see
+/// `tilt_scale`.
 fn world_to_screen_coords(
     point: Point2,
     screen_width: f32,
     screen_height: f32,
     zoom: f32,
     center: Point2,
+    angle: f32,
+    tilt: f32,
 ) -> Point2 {
-    let x = (point.x - center.x) * zoom + screen_width / 2.0;
-    let y = -(point.y - center.y) * zoom + screen_height / 2.0;
+    let rotated = Mat2::from_angle(angle) * (point - center);
+    let x = rotated.x * zoom + screen_width / 2.0;
+    let y = -rotated.y * zoom * tilt_scale(tilt) + screen_height / 2.0;
     Point2::new(x, y)
 }
 
-fn zoom_smooth(zoom_current: f32, zoom_target: f32) -> f32 {
-    const ZOOM_SMOOTH: f32 = 0.1;
-    zoom_current + (zoom_target - zoom_current) * ZOOM_SMOOTH
+/// Factor a `tilt` shrinks the vertical by. Leaning back squashes the plane
+/// by its cosine, and every point is then offset towards the middle by a
+/// share of its pseudo-depth, how far it lies behind the screen, for a hint
+/// of perspective. The offset grows with the distance from the middle, so
+/// this stays a plain linear map and lines stay straight.
+fn tilt_scale(tilt: f32) -> f32 {
+    tilt.cos() * (1.0 - TILT_DEPTH_OFFSET * tilt.sin())
+}
+
+fn screen_to_world_coords(
+    point: Point2,
+    screen_width: f32,
+    screen_height: f32,
+    zoom: f32,
+    center: Point2,
+    angle: f32,
+    tilt: f32,
+) -> Point2 {
+    let x = (point.x - screen_width / 2.0) / zoom;
+    let y = -(point.y - screen_height / 2.0) / (zoom * tilt_scale(tilt));
+    Mat2::from_angle(-angle) * Point2::new(x, y) + center
+}
+
+/// The camera of the main window, everything drawn in world coordinates
+/// goes through it.
+#[derive(Debug, Clone, Copy)]
+struct View {
+    screen_width: f32,
+    screen_height: f32,
+    zoom: f32,
+    center: Point2,
+    angle: f32,
+    /// Radians the plane leans back, 0 to look straight down on it.
+    tilt: f32,
+}
+
+impl View {
+    fn to_screen(self, point: Point2) -> Point2 {
+        let (w, h) = (self.screen_width, self.screen_height);
+        world_to_screen_coords(point, w, h, self.zoom, self.center, self.angle, self.tilt)
+    }
+
+    fn to_world(self, point: Point2) -> Point2 {
+        let (w, h) = (self.screen_width, self.screen_height);
+        screen_to_world_coords(point, w, h, self.zoom, self.center, self.angle, self.tilt)
+    }
+
+    /// Where a direction in the world points on screen, whose y points down.
+    fn to_screen_direction(self, dir: Vector2) -> Vector2 {
+        let rotated = Mat2::from_angle(self.angle) * dir;
+        Vector2::new(rotated.x, -rotated.y * tilt_scale(self.tilt)).normalize_or_zero()
+    }
+
+    /// `to_screen` as a matrix, for meshes built in world coordinates.
+    fn transform(self) -> Mat4 {
+        let turn = Mat2::from_angle(self.angle);
+        let scale = Vector2::new(self.zoom, -self.zoom * tilt_scale(self.tilt));
+        let (x, y) = (turn.x_axis * scale, turn.y_axis * scale);
+        let origin = self.to_screen(Point2::ZERO);
+        Mat4::from_cols(
+            x.extend(0.0).extend(0.0),
+            y.extend(0.0).extend(0.0),
+            Vec4::Z,
+            origin.extend(0.0).extend(1.0),
+        )
+    }
+}
+
+/// Move the zoom the share `smoothing` of the way to its target, 1 gets there
+/// right away.
+fn zoom_smooth(zoom_current: f32, zoom_target: f32, smoothing: f32) -> f32 {
+    zoom_current + (zoom_target - zoom_current) * smoothing
 }
 
-fn move_smooth(center_current: Point2, center_target: Point2) -> Point2 {
-    const MOVE_SMOOTH: f32 = 0.1;
+/// Move the center the share `smoothing` of the way to its target, 1 gets
+/// there right away.
+fn move_smooth(center_current: Point2, center_target: Point2, smoothing: f32) -> Point2 {
     Point2::new(
-        center_current.x + MOVE_SMOOTH * (center_target.x - center_current.x),
-        center_current.y + MOVE_SMOOTH * (center_target.y - center_current.y),
+        center_current.x + smoothing * (center_target.x - center_current.x),
+        center_current.y + smoothing * (center_target.y - center_current.y),
     )
 }
 
+/// Depth of the potential at the center of every cell of a grid `columns`
+/// wide over the view, row by row from the top left, from 0 for the
+/// shallowest to 1 for the deepest cell. Also returns the number of rows.
+///
+/// Depths are compressed logarithmically, otherwise the wells right at the
+/// bodies would be all there is to see.
+fn potential_depths(
+    actors: &[Actor],
+    config: &SimConfig,
+    view: View,
+    columns: u32,
+) -> (u32, Vec<f32>) {
+    let cell = view.screen_width / columns as f32;
+    let rows = (view.screen_height / cell).ceil() as u32;
+    let potentials: Vec<f32> = (0..rows)
+        .flat_map(|row| (0..columns).map(move |col| (col, row)))
+        .map(|(col, row)| {
+            let center = Point2::new(col as f32 + 0.5, row as f32 + 0.5) * cell;
+            potential_at(actors, config, view.to_world(center))
+        })
+        .collect();
+    let finite = || potentials.iter().copied().filter(|p| p.is_finite());
+    let shallowest = finite().fold(f32::MIN, f32::max);
+    let count = finite().count().max(1) as f32;
+    let mean_depth = finite().map(|p| shallowest - p).sum::<f32>() / count;
+    if mean_depth <= 0.0 {
+        return (rows, vec![0.0; potentials.len()]);
+    }
+    let compress = |p: f32| ((shallowest - p) / mean_depth).ln_1p();
+    let deepest = finite().map(compress).fold(0.0, f32::max);
+    let depths = potentials
+        .iter()
+        .map(|&p| {
+            if p.is_finite() {
+                compress(p) / deepest
+            } else {
+                1.0
+            }
+        })
+        .collect();
+    (rows, depths)
+}
+
+/// Mix of two RGBA colors, `t` of the way from `from` to `to`.
+fn mix_colors(from: u32, to: u32, t: f32) -> graphics::Color {
+    let (a, b) = (
+        graphics::Color::from_rgba_u32(from),
+        graphics::Color::from_rgba_u32(to),
+    );
+    let mix = |x: f32, y: f32| x + (y - x) * t;
+    graphics::Color::new(mix(a.r, b.r), mix(a.g, b.g), mix(a.b, b.b), mix(a.a, b.a))
+}
+
+/// The potential field under the bodies, only recomputed every
+/// `POTENTIAL_REFRESH_FRAMES`.
+#[derive(Default)]
+struct PotentialField {
+    mesh: Option<graphics::Mesh>,
+    age: u32,
+}
+
+fn draw_potential(
+    ctx: &mut Context,
+    field: &mut PotentialField,
+    sim: &Simulation,
+    columns: u32,
+    view: &View,
+) -> GameResult {
+    if field.age == 0 || field.mesh.is_none() {
+        let (rows, depths) = potential_depths(sim.actors(), sim.config(), *view, columns);
+        let cell = view.screen_width / columns as f32;
+        let mut mb = graphics::MeshBuilder::new();
+        for (i, depth) in depths.into_iter().enumerate() {
+            let (col, row) = (i as u32 % columns, i as u32 / columns);
+            let rect = graphics::Rect::new(col as f32 * cell, row as f32 * cell, cell, cell);
+            let color = mix_colors(POTENTIAL_SHALLOW_COLOR, POTENTIAL_DEEP_COLOR, depth);
+            mb.rectangle(graphics::DrawMode::fill(), rect, color)?;
+        }
+        field.mesh = if rows > 0 { Some(mb.build(ctx)?) } else { None };
+    }
+    field.age = (field.age + 1) % POTENTIAL_REFRESH_FRAMES;
+    match &field.mesh {
+        Some(mesh) => graphics::draw(ctx, mesh, DrawParam::default()),
+        None => Ok(()),
+    }
+}
+
+/// Tolerance in pixels for drawing a circle of `radius` pixels as polygon,
+/// finer with a higher `quality`. Clamped so the polygon has between
+/// `MIN_CIRCLE_SEGMENTS` and `MAX_CIRCLE_SEGMENTS` sides whatever the zoom.
+fn circle_tolerance(radius: f32, quality: f32) -> f32 {
+    // how far the sides of a regular polygon with that many sides stray
+    let sagitta = |segments: f32| radius * (1.0 - (std::f32::consts::PI / segments).cos());
+    (CIRCLE_TOLERANCE / quality).clamp(sagitta(MAX_CIRCLE_SEGMENTS), sagitta(MIN_CIRCLE_SEGMENTS))
+}
+
 fn draw_actor(
     ctx: &mut Context,
+    cache: &mut MeshCache,
     actor: &Actor,
-    world_coords: (f32, f32),
-    zoom: f32,
-    center: Point2,
+    color: u32,
+    view: &View,
 ) -> GameResult {
-    let (screen_w, screen_h) = world_coords;
-    let pos = world_to_screen_coords(actor.pos, screen_w, screen_h, zoom, center);
-    let circle = graphics::Mesh::new_circle(
+    let pos = view.to_screen(actor.pos);
+    // Radius + 1.0 in order to still draws actor for very far-out zooms.
+    let radius = (actor.radius * view.zoom + 1.0).round().max(1.0);
+    let circle = match bounded_entry(&mut cache.circles, radius as u32) {
+        Entry::Occupied(e) => e.into_mut(),
+        Entry::Vacant(e) => e.insert(graphics::Mesh::new_circle(
+            ctx,
+            graphics::DrawMode::fill(),
+            Point2::ZERO,
+            radius,
+            circle_tolerance(radius, cache.quality),
+            graphics::Color::WHITE,
+        )?),
+    };
+    graphics::draw(
         ctx,
-        graphics::DrawMode::fill(),
-        pos,
-        // Radius + 1.0 in order to still draws actor for very far-out zooms.
-        actor.radius * zoom + 1.0,
-        0.05 * actor.radius / zoom,
-        graphics::Color::from_rgba_u32(actor.color),
+        circle,
+        DrawParam::default()
+            .dest(pos)
+            .color(graphics::Color::from_rgba_u32(color)),
     )?;
-    graphics::draw(ctx, &circle, DrawParam::default())
+    if actor.tag == ActorType::BlackHole {
+        let ring = match bounded_entry(&mut cache.rings, radius as u32) {
+            Entry::Occupied(e) => e.into_mut(),
+            Entry::Vacant(e) => e.insert(graphics::Mesh::new_circle(
+                ctx,
+                graphics::DrawMode::stroke(2.0),
+                Point2::ZERO,
+                radius + 1.0,
+                circle_tolerance(radius + 1.0, cache.quality),
+                graphics::Color::from_rgba_u32(ACCRETION_RING_COLOR),
+            )?),
+        };
+        graphics::draw(ctx, ring, DrawParam::default().dest(pos))?;
+    }
+    Ok(())
+}
+
+/// Where and how to draw the star sprite for `actor`: centered on it, with
+/// the disc of the sprite as large as the circle `draw_actor` would draw.
+fn sprite_param(actor: &Actor, color: u32, view: &View) -> DrawParam {
+    let radius = (actor.radius * view.zoom + 1.0).round().max(1.0);
+    DrawParam::default()
+        .dest(view.to_screen(actor.pos))
+        .offset(Point2::new(0.5, 0.5))
+        .scale(Point2::splat(radius / STAR_SPRITE_CORE))
+        .color(graphics::Color::from_rgba_u32(color))
+}
+
+/// Radius of the glow around a sun of `mass` in multiples of its radius,
+/// heavier and so brighter suns shine further out.
+fn glow_factor(mass: f32) -> f32 {
+    GLOW_BASE + GLOW_MASS_SCALE * mass.ln_1p()
+}
+
+/// Soft halo fading out from the edge of `actor`, made of translucent
+/// circles stacked so they add up to a bright core.
+fn draw_glow(
+    ctx: &mut Context,
+    cache: &mut MeshCache,
+    actor: &Actor,
+    color: u32,
+    view: &View,
+) -> GameResult {
+    let pos = view.to_screen(actor.pos);
+    let radius = (actor.radius * view.zoom + 1.0).round().max(1.0);
+    let glow = (radius * glow_factor(actor.mass())).round();
+    let mesh = match bounded_entry(&mut cache.glows, (radius as u32, glow as u32)) {
+        Entry::Occupied(e) => e.into_mut(),
+        Entry::Vacant(e) => {
+            let mut builder = graphics::MeshBuilder::new();
+            for layer in 0..GLOW_LAYERS {
+                let r = glow - (glow - radius) * layer as f32 / GLOW_LAYERS as f32;
+                builder.circle(
+                    graphics::DrawMode::fill(),
+                    Point2::ZERO,
+                    r,
+                    circle_tolerance(r, cache.quality),
+                    graphics::Color::new(1.0, 1.0, 1.0, GLOW_ALPHA),
+                )?;
+            }
+            e.insert(builder.build(ctx)?)
+        }
+    };
+    graphics::draw(
+        ctx,
+        mesh,
+        DrawParam::default()
+            .dest(pos)
+            .color(graphics::Color::from_rgba_u32(color)),
+    )
 }
 
 fn draw_trace(
     ctx: &mut Context,
-    trace: &VecDeque<Point2>,
+    cache: &mut MeshCache,
+    actor: &Actor,
     color: u32,
-    world_coords: (f32, f32),
-    zoom: f32,
-    center: Point2,
+    view: &View,
 ) -> GameResult {
+    let (id, trace) = (actor.id, &actor.trace);
     if trace.len() >= 3 {
-        let (screen_w, screen_h) = world_coords;
-        let mut t = Vec::new();
-        for p in trace {
-            t.push(world_to_screen_coords(*p, screen_w, screen_h, zoom, center));
-        }
-        let trace_line =
-            graphics::Mesh::new_line(ctx, &t, 1.0, graphics::Color::from_rgba_u32(color))?;
-        graphics::draw(ctx, &trace_line, DrawParam::default())
+        let latest = trace[0];
+        let rebuild = match cache.traces.get(&id) {
+            Some((cached_latest, zoom, _)) => *cached_latest != latest || *zoom != view.zoom,
+            None => true,
+        };
+        if rebuild {
+            // Built in world coordinates, so the line width has to be divided by
+            // the zoom to end up one pixel wide on screen.
+            let t: Vec<Point2> = trace.iter().copied().collect();
+            let mesh = graphics::Mesh::new_line(ctx, &t, 1.0 / view.zoom, graphics::Color::WHITE)?;
+            cache.traces.insert(id, (latest, view.zoom, mesh));
+        }
+        graphics::draw(
+            ctx,
+            &cache.traces[&id].2,
+            DrawParam::default()
+                .transform(view.transform())
+                .color(graphics::Color::from_rgba_u32(color)),
+        )
     } else {
         Ok(())
     }
 }
 
-impl MainState {
-    fn new(ctx: &mut Context, suns: u32) -> GameResult<MainState> {
-        graphics::clear(ctx, (30, 40, 40, 255).into());
-        let (width, height) = graphics::drawable_size(ctx);
-        let s = MainState {
-            suns: create_suns(suns, height / 20.0 * suns as f32),
-            screen_width: width,
-            screen_height: height,
-            center: Point2::ZERO,
-            center_target: Point2::ZERO,
-            zoom: 1.0,
-            zoom_target: 1.0,
-            speed: 1.0,
-            running: true,
-            show_traces: true,
-        };
-        Ok(s)
-    }
+/// How the suns are drawn, the same in both halves of a split screen.
+struct BodyStyle<'a> {
+    glow: bool,
+    traces: Option<TraceStyle>,
+    color_mode: ColorMode,
+    sprite: Option<&'a graphics::Image>,
 }
 
-impl EventHandler for MainState {
-    fn update(&mut self, ctx: &mut Context) -> GameResult {
-        const DESIRED_FPS: u32 = 60;
-        let dt = self.speed / (DESIRED_FPS as f32);
-        while timer::check_update_time(ctx, DESIRED_FPS) {
-            if self.running {
-                update_vel_and_pos(&mut self.suns, dt);
-            }
-            // println!("{}", timer::fps(ctx));
+/// Glows, traces and the suns themselves with their pins.
+fn draw_bodies(
+    ctx: &mut Context,
+    cache: &mut MeshCache,
+    actors: &[Actor],
+    style: &BodyStyle,
+    view: &View,
+) -> GameResult {
+    let body_color = style.color_mode.painter(actors);
+    if style.glow {
+        // All glows go first, so no halo ends up on top of another body.
+        for s in actors.iter().filter(|s| s.tag != ActorType::BlackHole) {
+            draw_glow(ctx, cache, s, body_color(s), view)?;
         }
-        Ok(())
     }
-
-    fn draw(&mut self, ctx: &mut Context) -> GameResult {
-        graphics::clear(ctx, (30, 40, 40, 255).into());
-        let coords = (self.screen_width, self.screen_height);
-        self.zoom = zoom_smooth(self.zoom, self.zoom_target);
-        self.center = move_smooth(self.center, self.center_target);
-        for s in &self.suns {
-            if self.show_traces {
-                draw_trace(ctx, &s.trace, s.color, coords, self.zoom, self.center)
-                    .expect("failed to draw trace");
+    for s in actors {
+        if let Some(trace_style) = style.traces {
+            draw_trace(ctx, cache, s, trace_style.color(s), view)?;
+        }
+        let color = body_color(s);
+        match style.sprite {
+            // black holes keep their dark disc and accretion ring
+            Some(sprite) if s.tag != ActorType::BlackHole => {
+                graphics::draw(ctx, sprite, sprite_param(s, color, view))?
             }
-            draw_actor(ctx, s, coords, self.zoom, self.center).expect("failed to draw a sun");
+            _ => draw_actor(ctx, cache, s, color, view)?,
+        }
+        if s.pinned {
+            draw_highlight(ctx, s, PINNED_COLOR, view)?;
         }
-        graphics::present(ctx)?;
-        timer::yield_now();
-        Ok(())
     }
+    Ok(())
+}
 
-    fn key_down_event(
-        &mut self,
-        ctx: &mut Context,
-        keycode: KeyCode,
-        _keymod: KeyMods,
-        _repeat: bool,
+/// Format simulated seconds as e.g. `1 h 02 min 03.4 s`, leaving out
+/// leading units which are zero.
+fn format_sim_time(seconds: f32) -> String {
+    let total = seconds.max(0.0);
+    let hours = (total / 3600.0).floor();
+    let minutes = ((total - hours * 3600.0) / 60.0).floor();
+    let secs = total - hours * 3600.0 - minutes * 60.0;
+    if hours > 0.0 {
+        format!("{} h {:02} min {:04.1} s", hours, minutes, secs)
+    } else if minutes > 0.0 {
+        format!("{} min {:04.1} s", minutes, secs)
+    } else {
+        format!("{:.1} s", secs)
+    }
+}
+
+fn draw_highlight(ctx: &mut Context, actor: &Actor, color: u32, view: &View) -> GameResult {
+    let pos = view.to_screen(actor.pos);
+    let ring = graphics::Mesh::new_circle(
+        ctx,
+        graphics::DrawMode::stroke(1.5),
+        pos,
+        actor.radius * view.zoom + 1.0 + SELECTION_MARGIN,
+        0.5,
+        graphics::Color::from_rgba_u32(color),
+    )?;
+    graphics::draw(ctx, &ring, DrawParam::default())
+}
+
+/// Double-headed arrow across `actor` towards `neighbor` showing how much
+/// the tides of `neighbor` stretch it, on a log scale.
+fn draw_tides(
+    ctx: &mut Context,
+    actor: &Actor,
+    neighbor: &Actor,
+    g: f32,
+    view: &View,
+) -> GameResult {
+    let distance = actor.pos.distance(neighbor.pos);
+    let tidal = tidal_acceleration(g, neighbor.mass(), actor.radius, distance);
+    let half_len = actor.radius * view.zoom + 1.0 + TIDE_SCALE * tidal.ln_1p();
+    let dir = view.to_screen_direction((neighbor.pos - actor.pos) / distance);
+    let side = dir.perp() * TIDE_HEAD / 2.0;
+    let pos = view.to_screen(actor.pos);
+    let color = graphics::Color::from_rgba_u32(TIDE_COLOR);
+    let mut mb = graphics::MeshBuilder::new();
+    mb.line(&[pos - dir * half_len, pos + dir * half_len], 1.5, color)?;
+    for outwards in [-dir, dir] {
+        let tip = pos + outwards * half_len;
+        let base = tip - outwards * TIDE_HEAD;
+        mb.line(&[base + side, tip, base - side], 1.5, color)?;
+    }
+    let mesh = mb.build(ctx)?;
+    graphics::draw(ctx, &mesh, DrawParam::default())
+}
+
+fn draw_prediction(ctx: &mut Context, path: &[Point2], view: &View) -> GameResult {
+    let color = graphics::Color::from_rgba_u32(PREDICTION_COLOR);
+    let mut mb = graphics::MeshBuilder::new();
+    let mut dashes = 0;
+    for dash in path.chunks(PREDICTION_DASH).step_by(2) {
+        if dash.len() >= 2 {
+            let points: Vec<Point2> = dash.iter().map(|p| view.to_screen(*p)).collect();
+            mb.line(&points, 1.0, color)?;
+            dashes += 1;
+        }
+    }
+    if dashes == 0 {
+        return Ok(());
+    }
+    let mesh = mb.build(ctx)?;
+    graphics::draw(ctx, &mesh, DrawParam::default())
+}
+
+fn draw_flash(ctx: &mut Context, flash: &Flash, view: &View) -> GameResult {
+    let pos = view.to_screen(flash.pos);
+    let remaining = flash.lifetime / FLASH_LIFETIME;
+    let mut color = graphics::Color::from_rgba_u32(FLASH_COLOR);
+    color.a = remaining * flash.intensity;
+    let ring = graphics::Mesh::new_circle(
+        ctx,
+        graphics::DrawMode::stroke(2.0),
+        pos,
+        1.0 + FLASH_MAX_RADIUS * flash.intensity * (1.0 - remaining),
+        0.5,
+        color,
+    )?;
+    graphics::draw(ctx, &ring, DrawParam::default())
+}
+
+fn draw_fading_trace(ctx: &mut Context, trace: &FadingTrace, view: &View) -> GameResult {
+    let points: Vec<Point2> = trace.points.iter().map(|p| view.to_screen(*p)).collect();
+    let mut color = graphics::Color::from_rgba_u32(trace.color);
+    color.a *= trace.lifetime / TRACE_FADE_TIME;
+    let line = graphics::Mesh::new_line(ctx, &points, 1.0, color)?;
+    graphics::draw(ctx, &line, DrawParam::default())
+}
+
+fn draw_annotation(ctx: &mut Context, annotation: &Annotation, view: &View) -> GameResult {
+    let pos = view.to_screen(annotation.pos);
+    let mut color = graphics::Color::from_rgba_u32(ANNOTATION_COLOR);
+    color.a = (annotation.lifetime / ANNOTATION_LIFETIME).min(1.0);
+    graphics::draw(
+        ctx,
+        &graphics::Text::new(annotation.text.as_str()),
+        DrawParam::default()
+            .dest(pos + Point2::new(8.0, -8.0))
+            .color(color),
+    )
+}
+
+/// Where to put the id labels of `actors`, heaviest first. Bodies too small
+/// on screen or outside of it get none, nor do bodies whose label would cover
+/// one already placed, so dense regions stay readable.
+fn id_labels(actors: &[Actor], view: View) -> Vec<(u32, Point2)> {
+    let mut order: Vec<&Actor> = actors.iter().collect();
+    order.sort_by(|a, b| b.mass().total_cmp(&a.mass()).then(a.id.cmp(&b.id)));
+    let screen = graphics::Rect::new(0.0, 0.0, view.screen_width, view.screen_height);
+    let mut placed: Vec<graphics::Rect> = Vec::new();
+    let mut labels = Vec::new();
+    for a in order {
+        let radius = a.radius * view.zoom;
+        let pos = view.to_screen(a.pos);
+        if radius < ID_MIN_RADIUS || !screen.contains(pos) {
+            continue;
+        }
+        // roughly the size of the text, digits are about 0.6 times as wide
+        // as high
+        let width = a.id.to_string().len() as f32 * 0.6 * ID_TEXT_SIZE;
+        let corner = pos + Point2::new(radius + 2.0, -ID_TEXT_SIZE / 2.0);
+        let rect = graphics::Rect::new(corner.x, corner.y, width, ID_TEXT_SIZE);
+        if placed.iter().all(|p| !p.overlaps(&rect)) {
+            placed.push(rect);
+            labels.push((a.id, corner));
+        }
+    }
+    labels
+}
+
+/// Indices of the bodies to connect to the center of mass and the alpha of
+/// their lines, which fade with the mass so light bodies hardly show.
+fn barycenter_lines(actors: &[Actor]) -> Vec<(usize, f32)> {
+    let mut order: Vec<usize> = (0..actors.len()).collect();
+    order.sort_by(|&a, &b| actors[b].mass().total_cmp(&actors[a].mass()));
+    let Some(&heaviest) = order.first() else {
+        return Vec::new();
+    };
+    let max_mass = actors[heaviest].mass();
+    order
+        .into_iter()
+        .take(BARYCENTER_LINES_MAX)
+        .map(|idx| {
+            let share = actors[idx].mass() / max_mass;
+            (idx, BARYCENTER_LINE_ALPHA * share.sqrt())
+        })
+        .filter(|&(_, alpha)| alpha >= BARYCENTER_LINE_MIN_ALPHA)
+        .collect()
+}
+
+fn draw_barycenter_lines(ctx: &mut Context, actors: &[Actor], view: &View) -> GameResult {
+    let center = view.to_screen(center_of_mass(actors));
+    let mut mb = graphics::MeshBuilder::new();
+    let mut lines = 0;
+    for (idx, alpha) in barycenter_lines(actors) {
+        let pos = view.to_screen(actors[idx].pos);
+        // a line without length cannot be drawn
+        if pos.distance_squared(center) < 1.0 {
+            continue;
+        }
+        let mut color = graphics::Color::from_rgba_u32(BARYCENTER_LINE_COLOR);
+        color.a = alpha;
+        mb.line(&[pos, center], 1.0, color)?;
+        lines += 1;
+    }
+    if lines == 0 {
+        return Ok(());
+    }
+    let mesh = mb.build(ctx)?;
+    graphics::draw(ctx, &mesh, DrawParam::default())
+}
+
+/// A line between every pair of bodies heading for a hit within
+/// `COURSE_HORIZON`, the sooner the hit the redder and stronger the line.
+fn draw_collision_courses(ctx: &mut Context, actors: &[Actor], view: &View) -> GameResult {
+    let mut mb = graphics::MeshBuilder::new();
+    let mut lines = 0;
+    for (a, b, time) in collision_courses(actors, COURSE_HORIZON) {
+        let (from, to) = (view.to_screen(actors[a].pos), view.to_screen(actors[b].pos));
+        // a line without length cannot be drawn
+        if from.distance_squared(to) < 1.0 {
+            continue;
+        }
+        let urgency = 1.0 - time / COURSE_HORIZON;
+        let color = mix_colors(COURSE_LATE_COLOR, COURSE_SOON_COLOR, urgency);
+        mb.line(&[from, to], 1.0, color)?;
+        lines += 1;
+    }
+    if lines == 0 {
+        return Ok(());
+    }
+    let mesh = mb.build(ctx)?;
+    graphics::draw(ctx, &mesh, DrawParam::default())
+}
+
+fn draw_ids(ctx: &mut Context, actors: &[Actor], view: &View) -> GameResult {
+    for (id, corner) in id_labels(actors, *view) {
+        let text = graphics::TextFragment::new(id.to_string()).scale(ID_TEXT_SIZE);
+        graphics::draw(
+            ctx,
+            &graphics::Text::new(text),
+            DrawParam::default()
+                .dest(corner)
+                .color(graphics::Color::from_rgba_u32(ID_COLOR)),
+        )?;
+    }
+    Ok(())
+}
+
+/// Mass, distance to the barycenter and speed of `actor`, in physical units
+/// if there are any.
+fn describe_body(actor: &Actor, barycenter: Point2, units: Option<UnitSystem>) -> String {
+    let distance = actor.pos.distance(barycenter);
+    let speed = actor.velocity().length();
+    match units {
+        Some(u) => format!(
+            "{}, {} from center, {}",
+            u.format_mass(actor.mass()),
+            u.format_length(distance),
+            u.format_speed(speed)
+        ),
+        None => format!(
+            "mass {:.2}, {:.1} from center, speed {:.1}",
+            actor.mass(),
+            distance,
+            speed
+        ),
+    }
+}
+
+/// Indices of the `n` bodies closest to `point`, closest first.
+fn nearest_bodies(actors: &[Actor], point: Point2, n: usize) -> Vec<usize> {
+    let n = n.min(actors.len());
+    if n == 0 {
+        return Vec::new();
+    }
+    let by_distance = |a: &usize, b: &usize| {
+        let distance = |i: usize| actors[i].pos.distance_squared(point);
+        distance(*a).total_cmp(&distance(*b))
+    };
+    let mut order: Vec<usize> = (0..actors.len()).collect();
+    order.select_nth_unstable_by(n - 1, by_distance);
+    order.truncate(n);
+    order.sort_by(by_distance);
+    order
+}
+
+/// Panel next to the cursor with mass and speed of the bodies closest to it.
+fn draw_inspect(ctx: &mut Context, state: &MainState) -> GameResult {
+    let mouse = ggez::input::mouse::position(ctx);
+    let mouse = Point2::new(mouse.x, mouse.y);
+    let cursor = state.view().to_world(mouse);
+    let actors = state.sim.actors();
+    let nearest = nearest_bodies(actors, cursor, state.settings.inspect_count);
+    if nearest.is_empty() {
+        return Ok(());
+    }
+    let units = state.settings.units;
+    let lines: Vec<String> = nearest
+        .into_iter()
+        .map(|idx| {
+            let a = &actors[idx];
+            let (mass, speed) = (a.mass(), a.velocity().length());
+            match units {
+                Some(u) => format!(
+                    "{}: {}, {}",
+                    a.id,
+                    u.format_mass(mass),
+                    u.format_speed(speed)
+                ),
+                None => format!("{}: mass {:.2}, speed {:.1}", a.id, mass, speed),
+            }
+        })
+        .collect();
+    let text = graphics::Text::new(lines.join("\n"));
+    let size = text.dimensions(ctx);
+    let (w, h) = (
+        size.w + 2.0 * INSPECT_PADDING,
+        size.h + 2.0 * INSPECT_PADDING,
+    );
+    // Kept on screen, also with the cursor in a corner.
+    let corner = Point2::new(
+        (mouse.x + INSPECT_OFFSET)
+            .min(state.screen_width - w)
+            .max(0.0),
+        (mouse.y + INSPECT_OFFSET)
+            .min(state.screen_height - h)
+            .max(0.0),
+    );
+    let background = graphics::Mesh::new_rectangle(
+        ctx,
+        graphics::DrawMode::fill(),
+        graphics::Rect::new(corner.x, corner.y, w, h),
+        graphics::Color::from_rgba_u32(INSPECT_BACKGROUND),
+    )?;
+    graphics::draw(ctx, &background, DrawParam::default())?;
+    graphics::draw(
+        ctx,
+        &text,
+        DrawParam::default()
+            .dest(corner + Point2::splat(INSPECT_PADDING))
+            .color(graphics::Color::from_rgba_u32(state.theme.hud)),
+    )
+}
+
+/// All keybindings over the dimmed screen, the simulation keeps running
+/// behind them.
+fn draw_help(ctx: &mut Context, state: &MainState) -> GameResult {
+    let mut dim = graphics::Color::from_rgba_u32(state.theme.background);
+    dim.a = HELP_DIM;
+    let screen = graphics::Rect::new(0.0, 0.0, state.screen_width, state.screen_height);
+    let background = graphics::Mesh::new_rectangle(ctx, graphics::DrawMode::fill(), screen, dim)?;
+    graphics::draw(ctx, &background, DrawParam::default())?;
+    let mut lines: Vec<String> = KEYBINDINGS
+        .iter()
+        .map(|b| help_line(b, b.state.map(|on| on(state))))
+        .collect();
+    lines.push(String::new());
+    lines.push("press any key to close".to_string());
+    let text = graphics::Text::new(lines.join("\n"));
+    graphics::draw(
+        ctx,
+        &text,
+        DrawParam::default()
+            .dest(Point2::splat(HELP_MARGIN))
+            .color(graphics::Color::from_rgba_u32(state.theme.hud)),
+    )
+}
+
+fn draw_hud(ctx: &mut Context, state: &MainState) -> GameResult {
+    let mut status = if state.running { "running" } else { "paused" }.to_string();
+    if state.slowmo < 0.99 {
+        status += &format!(", slow motion {:.2}x", state.slowmo);
+    }
+    if let Some(idx) = state.selected_index() {
+        let actors = state.sim.actors();
+        let barycenter = center_of_mass(actors);
+        status += "\nselected: ";
+        status += &describe_body(&actors[idx], barycenter, state.settings.units);
+    }
+    let mut time = format_sim_time(state.sim.time());
+    if let Some(units) = state.settings.units {
+        time += &format!(" ({})", units.format_time(state.sim.time()));
+    }
+    // velocity dispersion, the temperature of the cluster
+    let dispersion = velocity_dispersion(state.sim.actors());
+    let mut sigma = format!("{:.2}", dispersion);
+    if let Some(units) = state.settings.units {
+        sigma += &format!(" ({})", units.format_speed(dispersion));
+    }
+    let events = state.sim.counters();
+    let hud = graphics::Text::new(format!(
+        "speed:  {:.3}x\nzoom:   {:.3}x\nbodies: {}\ntime:   {}\nfps:    {:.0}\n\
+         hits:   {} ({} merged, {} absorbed)\nnovae:  {}\nejected: {}\nvirial: {}\n\
+         sigma:  {}\ntraces: every {} steps\n{}",
+        state.speed,
+        state.zoom,
+        state.sim.actors().len(),
+        time,
+        timer::fps(ctx),
+        events.collisions,
+        events.merges,
+        events.absorptions,
+        events.supernovae,
+        events.ejections,
+        state
+            .virial
+            .map_or_else(|| "-".to_string(), |v| format!("{:.2}", v)),
+        sigma,
+        state.sim.trace_interval(),
+        status
+    ));
+    graphics::draw(
+        ctx,
+        &hud,
+        DrawParam::default()
+            .dest(Point2::new(HUD_MARGIN, HUD_MARGIN))
+            .color(graphics::Color::from_rgba_u32(state.theme.hud)),
+    )
+}
+
+/// Lowest, average and highest frames per second of the given frame times.
+fn fps_stats(frame_times: &VecDeque<f32>) -> Option<(f32, f32, f32)> {
+    let longest = frame_times.iter().copied().fold(f32::MIN, f32::max);
+    let shortest = frame_times.iter().copied().fold(f32::MAX, f32::min);
+    if frame_times.is_empty() || shortest <= 0.0 {
+        return None;
+    }
+    let average = frame_times.iter().sum::<f32>() / frame_times.len() as f32;
+    Some((1.0 / longest, 1.0 / average, 1.0 / shortest))
+}
+
+/// Frame time graph with min/avg/max fps in the top right corner.
+fn draw_perf(ctx: &mut Context, state: &MainState) -> GameResult {
+    let origin = Point2::new(state.screen_width - PERF_GRAPH_W - HUD_MARGIN, HUD_MARGIN);
+    let bounds = graphics::Rect::new(origin.x, origin.y, PERF_GRAPH_W, PERF_GRAPH_H);
+    let mut mb = graphics::MeshBuilder::new();
+    mb.rectangle(
+        graphics::DrawMode::fill(),
+        bounds,
+        graphics::Color::from_rgba_u32(PERF_BACKGROUND),
+    )?;
+    let bar_w = PERF_GRAPH_W / PERF_HISTORY as f32;
+    let bar_color = graphics::Color::from_rgba_u32(PERF_BAR_COLOR);
+    for (i, t) in state.frame_times.iter().enumerate() {
+        let h = (t / PERF_GRAPH_SCALE).min(1.0) * PERF_GRAPH_H;
+        let bar = graphics::Rect::new(
+            origin.x + i as f32 * bar_w,
+            origin.y + PERF_GRAPH_H - h,
+            bar_w,
+            h,
+        );
+        mb.rectangle(graphics::DrawMode::fill(), bar, bar_color)?;
+    }
+    let mesh = mb.build(ctx)?;
+    graphics::draw(ctx, &mesh, DrawParam::default())?;
+    let stats = match fps_stats(&state.frame_times) {
+        Some((min, avg, max)) => format!("fps {:.0} / {:.0} / {:.0}", min, avg, max),
+        None => "fps -".to_string(),
+    };
+    let text = graphics::Text::new(stats);
+    graphics::draw(
+        ctx,
+        &text,
+        DrawParam::default()
+            .dest(origin + Point2::new(4.0, 4.0))
+            .color(graphics::Color::from_rgba_u32(state.theme.hud)),
+    )
+}
+
+/// Energy samples as points on a plot of size `w` x `h`, scaled to span the
+/// full height, and the lowest and highest energy shown. The oldest sample is
+/// on the left edge, the newest on the right.
+fn energy_plot(samples: &VecDeque<(f32, f32)>, w: f32, h: f32) -> Option<(Vec<Point2>, f32, f32)> {
+    let (t0, t1) = (samples.front()?.0, samples.back()?.0);
+    let (min, max) = samples
+        .iter()
+        .fold((f32::MAX, f32::MIN), |(min, max), (_, e)| {
+            (min.min(*e), max.max(*e))
+        });
+    let span = |a: f32, b: f32| if b > a { b - a } else { 1.0 };
+    let points = samples
+        .iter()
+        .map(|(t, e)| {
+            let x = (t - t0) / span(t0, t1) * w;
+            let y = h - (e - min) / span(min, max) * h;
+            Point2::new(x, y)
+        })
+        .collect();
+    Some((points, min, max))
+}
+
+/// Total energy over time with auto-scaled y-axis in the bottom left corner.
+fn draw_energy(ctx: &mut Context, state: &MainState) -> GameResult {
+    let origin = Point2::new(
+        HUD_MARGIN,
+        state.screen_height - ENERGY_GRAPH_H - HUD_MARGIN,
+    );
+    let bounds = graphics::Rect::new(origin.x, origin.y, ENERGY_GRAPH_W, ENERGY_GRAPH_H);
+    let mut mb = graphics::MeshBuilder::new();
+    mb.rectangle(
+        graphics::DrawMode::fill(),
+        bounds,
+        graphics::Color::from_rgba_u32(PERF_BACKGROUND),
+    )?;
+    let plot = energy_plot(&state.energy, ENERGY_GRAPH_W, ENERGY_GRAPH_H);
+    if let Some((points, _, _)) = &plot {
+        if points.len() >= 2 {
+            let points: Vec<Point2> = points.iter().map(|p| origin + *p).collect();
+            let color = graphics::Color::from_rgba_u32(ENERGY_LINE_COLOR);
+            mb.line(&points, 1.5, color)?;
+        }
+    }
+    let mesh = mb.build(ctx)?;
+    graphics::draw(ctx, &mesh, DrawParam::default())?;
+    let Some((_, min, max)) = plot else {
+        return Ok(());
+    };
+    let (t0, t1) = (state.energy[0].0, state.energy[state.energy.len() - 1].0);
+    let labels = [
+        (format!("E {:.4e}", max), Point2::new(4.0, 4.0)),
+        (
+            format!("E {:.4e}", min),
+            Point2::new(4.0, ENERGY_GRAPH_H - 36.0),
+        ),
+        (
+            format!("t {:.0} .. {:.0} s", t0, t1),
+            Point2::new(4.0, ENERGY_GRAPH_H - 18.0),
+        ),
+    ];
+    for (label, offset) in labels {
+        graphics::draw(
+            ctx,
+            &graphics::Text::new(label),
+            DrawParam::default()
+                .dest(origin + offset)
+                .color(graphics::Color::from_rgba_u32(state.theme.hud)),
+        )?;
+    }
+    Ok(())
+}
+
+/// Center and zoom of a camera showing all `points` on the minimap.
+///
+/// This is independent of the main camera and recomputed every frame, so the
+/// minimap zooms out as the bodies spread.
+fn minimap_view(points: impl Iterator<Item = Point2>) -> (Point2, f32) {
+    let (min, max) = points.fold(
+        (Point2::splat(f32::MAX), Point2::splat(f32::MIN)),
+        |(min, max), p| (min.min(p), max.max(p)),
+    );
+    if min.x > max.x {
+        return (Point2::ZERO, 1.0);
+    }
+    let extent = (max - min).max_element().max(1.0);
+    let zoom = MINIMAP_SIZE * (1.0 - 2.0 * MINIMAP_PADDING) / extent;
+    ((min + max) / 2.0, zoom)
+}
+
+fn draw_minimap(ctx: &mut Context, state: &MainState) -> GameResult {
+    let origin = Point2::new(
+        state.screen_width - MINIMAP_SIZE - MINIMAP_MARGIN,
+        state.screen_height - MINIMAP_SIZE - MINIMAP_MARGIN,
+    );
+    let bounds = graphics::Rect::new(origin.x, origin.y, MINIMAP_SIZE, MINIMAP_SIZE);
+    // Turned like the main view, so its viewport stays a rectangle.
+    let turn = Mat2::from_angle(state.view_angle);
+    let (center, zoom) = minimap_view(state.sim.actors().iter().map(|a| turn * a.pos));
+    let to_minimap = |p: Point2| {
+        origin
+            + world_to_screen_coords(turn * p, MINIMAP_SIZE, MINIMAP_SIZE, zoom, center, 0.0, 0.0)
+    };
+
+    let mut mb = graphics::MeshBuilder::new();
+    mb.rectangle(
+        graphics::DrawMode::fill(),
+        bounds,
+        graphics::Color::from_rgba_u32(MINIMAP_BACKGROUND),
+    )?;
+    mb.rectangle(
+        graphics::DrawMode::stroke(1.0),
+        bounds,
+        graphics::Color::from_rgba_u32(MINIMAP_BORDER_COLOR),
+    )?;
+    for a in state.sim.actors() {
+        let color = match a.tag {
+            ActorType::Sun => a.color,
+            ActorType::BlackHole => ACCRETION_RING_COLOR,
+        };
+        mb.circle(
+            graphics::DrawMode::fill(),
+            to_minimap(a.pos),
+            1.5,
+            0.5,
+            graphics::Color::from_rgba_u32(color),
+        )?;
+    }
+
+    // the main viewport, clipped to the minimap
+    let view = state.view();
+    let top_left = view.to_world(Point2::ZERO);
+    let bottom_right = view.to_world(Point2::new(view.screen_width, view.screen_height));
+    let min = to_minimap(top_left).max(bounds.point().into());
+    let max = to_minimap(bottom_right).min(Point2::new(bounds.right(), bounds.bottom()));
+    if min.x < max.x && min.y < max.y {
+        mb.rectangle(
+            graphics::DrawMode::stroke(1.0),
+            graphics::Rect::new(min.x, min.y, max.x - min.x, max.y - min.y),
+            graphics::Color::from_rgba_u32(MINIMAP_VIEWPORT_COLOR),
+        )?;
+    }
+    let mesh = mb.build(ctx)?;
+    graphics::draw(ctx, &mesh, DrawParam::default())
+}
+
+/// The `--events` line of a collision at simulated time `time`.
+fn event_line(time: f32, event: &CollisionEvent) -> String {
+    let (a, b) = event.ids;
+    let kind = match event.kind {
+        CollisionKind::Bounce => "collision",
+        CollisionKind::Merge => "merge",
+        CollisionKind::Absorption => "absorption",
+        CollisionKind::Fragmentation => "fragmentation",
+        CollisionKind::Supernova => return format!("t={:.2} supernova id={}", time, a),
+    };
+    format!(
+        "t={:.2} {} id={} id={} rel_speed={:.2}",
+        time, kind, a, b, event.speed
+    )
+}
+
+/// Print the collisions and ejections of the last step and flush, so the
+/// lines stream even when stdout is a pipe.
+fn print_events(
+    out: &mut impl Write,
+    sim: &Simulation,
+    collisions: &[CollisionEvent],
+) -> io::Result<()> {
+    for event in collisions {
+        writeln!(out, "{}", event_line(sim.time(), event))?;
+    }
+    for id in sim.ejected() {
+        writeln!(out, "t={:.2} ejection id={}", sim.time(), id)?;
+    }
+    out.flush()
+}
+
+/// Periodic checkpoints if asked for, creating their directory.
+fn checkpoints(settings: &Settings) -> io::Result<Option<Checkpoints>> {
+    match settings.checkpoint_interval {
+        Some(interval) => {
+            fs::create_dir_all(&settings.checkpoint_dir)?;
+            let dir = settings.checkpoint_dir.clone();
+            Ok(Some(Checkpoints::new(
+                dir,
+                interval,
+                settings.checkpoint_keep,
+            )))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Run `steps` physics steps without opening a window.
+pub fn run_headless(settings: Settings, config: SimConfig, steps: u64) -> GameResult {
+    let mut rng = StdRng::seed_from_u64(settings.seed);
+    info!("seed {}", settings.seed);
+    let (bodies, _) = initial_bodies(&settings, SCREEN_H, &config.profile, &mut rng)?;
+    let mut sim = simulation(bodies, config, &settings);
+    let dt = 1.0 / settings.fps as f32;
+    // Frames are spread evenly over the run.
+    let stride = (steps / settings.gif_frames as u64).max(1);
+    let mut recorder = match &settings.gif {
+        Some(path) => {
+            let (w, h) = (SCREEN_W as u32, SCREEN_H as u32);
+            let size = |s: u32| (s / settings.gif_scale).max(1) as u16;
+            let delay = (stride * 100 / settings.fps as u64).max(2) as u16;
+            let file = BufWriter::new(File::create(path)?);
+            Some(GifRecorder::new(file, size(w), size(h), delay)?)
+        }
+        None => None,
+    };
+    let zoom = 1.0 / settings.gif_scale as f32;
+    let camera_path = settings
+        .camera_path
+        .as_deref()
+        .map(CameraPath::load)
+        .transpose()?;
+    let mut checkpoints = checkpoints(&settings)?;
+    let mut trajectory = match &settings.trajectory {
+        Some(path) => {
+            let file = BufWriter::new(File::create(path)?);
+            Some(TrajectoryWriter::new(file, settings.trajectory_interval)?)
+        }
+        None => None,
+    };
+    let mut stdout = io::stdout().lock();
+    for step in 0..steps {
+        if let Some(trajectory) = &mut trajectory {
+            trajectory.update(&sim)?;
+        }
+        if let Some(recorder) = &mut recorder {
+            if step % stride == 0 && recorder.frames() < settings.gif_frames {
+                let camera = camera_path.as_ref().and_then(|p| p.at(sim.time()));
+                let (center, path_zoom) = camera.unwrap_or((Point2::ZERO, 1.0));
+                recorder.add_frame(sim.actors(), center, zoom * path_zoom)?;
+            }
+        }
+        let collisions = sim.step(dt);
+        if settings.events {
+            print_events(&mut stdout, &sim, &collisions)?;
+        }
+        if let Some(checkpoints) = &mut checkpoints {
+            // no frames to hold up, so no checkpoint is skipped
+            if checkpoints.is_due(sim.time()) {
+                checkpoints.wait()?;
+            }
+            checkpoints.update(&sim, None)?;
+        }
+    }
+    if let Some(checkpoints) = &mut checkpoints {
+        checkpoints.wait()?;
+    }
+    if let Some(mut trajectory) = trajectory {
+        // the end of the run is always in, due or not
+        trajectory.sample(&sim)?;
+        trajectory.finish()?;
+    }
+    if let Some(recorder) = recorder {
+        let frames = recorder.frames();
+        recorder.finish()?;
+        info!("wrote {} gif frames", frames);
+    }
+    if let Some(path) = &settings.output {
+        write_json(&sim, None, path)?;
+    }
+    if let Some(path) = &settings.svg {
+        let trace_color = |s: &Actor| settings.theme.traces.color(s);
+        write_svg(sim.actors(), trace_color, |s: &Actor| s.color, path)?;
+    }
+    Ok(())
+}
+
+/// Step the simulation `steps` times as fast as possible and print how long
+/// that took as `key=value` lines.
+pub fn run_bench(
+    settings: Settings,
+    config: SimConfig,
+    steps: u64,
+    out: &mut impl Write,
+) -> GameResult {
+    let mut rng = StdRng::seed_from_u64(settings.seed);
+    let (bodies, _) = initial_bodies(&settings, SCREEN_H, &config.profile, &mut rng)?;
+    let body_count = bodies.len();
+    let mut sim = simulation(bodies, config, &settings);
+    let dt = 1.0 / settings.fps as f32;
+    let start = Instant::now();
+    for _ in 0..steps {
+        sim.step(dt);
+    }
+    let wall_time = start.elapsed().as_secs_f64();
+    let (actors, config) = (sim.actors(), sim.config());
+    let energy = kinetic_energy(actors) + potential_energy(actors, config);
+    writeln!(out, "bodies={}", body_count)?;
+    writeln!(out, "final_bodies={}", actors.len())?;
+    writeln!(out, "steps={}", steps)?;
+    writeln!(out, "wall_time_s={:.6}", wall_time)?;
+    writeln!(out, "steps_per_second={:.1}", steps as f64 / wall_time)?;
+    writeln!(out, "final_energy={}", energy)?;
+    Ok(())
+}
+
+/// The bodies and camera of the scenario to load, or a fresh random galaxy.
+fn initial_bodies(
+    settings: &Settings,
+    screen_height: f32,
+    profile: &PhysicsProfile,
+    rng: &mut StdRng,
+) -> GameResult<(Vec<Actor>, CameraState)> {
+    let (mut bodies, camera) = if let Some(path) = &settings.load {
+        info!("loading {}", path.display());
+        let scenario = read_scenario(path)?;
+        (scenario.bodies, scenario.camera)
+    } else if let Some(path) = &settings.catalog {
+        let bodies = load_catalog(path, &settings.catalog_units, profile)?;
+        (bodies, CameraState::default())
+    } else {
+        let bodies = spawn_galaxy(settings, screen_height, profile, rng);
+        (bodies, CameraState::default())
+    };
+    if settings.pin_heaviest {
+        pin_heaviest(&mut bodies);
+    }
+    Ok((bodies, camera))
+}
+
+/// Pin the heaviest of `bodies` in place as a central star for the others
+/// to orbit.
+fn pin_heaviest(bodies: &mut [Actor]) {
+    if let Some(idx) = most_massive(bodies) {
+        bodies[idx].pinned = true;
+    }
+}
+
+/// The starting bodies and camera along with the random generator seeded
+/// from `settings.seed` right after spawning them, the same settings always
+/// give the same start.
+fn seeded_start(
+    settings: &Settings,
+    screen_height: f32,
+    profile: &PhysicsProfile,
+) -> GameResult<(Vec<Actor>, CameraState, StdRng)> {
+    let mut rng = StdRng::seed_from_u64(settings.seed);
+    let (bodies, camera) = initial_bodies(settings, screen_height, profile, &mut rng)?;
+    Ok((bodies, camera, rng))
+}
+
+/// A simulation of `bodies` with the per-run options of `settings` applied.
+fn simulation(bodies: Vec<Actor>, config: SimConfig, settings: &Settings) -> Simulation {
+    let mut sim = Simulation::new(bodies, config);
+    sim.set_trace_budget(settings.trace_budget);
+    sim.set_recenter_momentum(settings.recenter_momentum);
+    sim
+}
+
+fn galaxy_radius(bodies: u32, screen_height: f32) -> f32 {
+    screen_height / 20.0 * bodies as f32
+}
+
+fn spawn_galaxy(
+    settings: &Settings,
+    screen_height: f32,
+    profile: &PhysicsProfile,
+    rng: &mut StdRng,
+) -> Vec<Actor> {
+    let counts = settings.class_counts.as_ref();
+    let suns = counts.map_or(settings.suns, ClassCounts::total);
+    let black_holes = settings.black_holes;
+    info!("spawning {} suns and {} black holes", suns, black_holes);
+    let galaxy_radius = settings
+        .galaxy_radius
+        .unwrap_or_else(|| galaxy_radius(suns, screen_height));
+    let recenter = settings.recenter_momentum;
+    let chilled;
+    let profile = if settings.cold {
+        chilled = PhysicsProfile {
+            max_starting_velocity: profile.max_starting_velocity * COLD_VELOCITY_SHARE,
+            ..*profile
+        };
+        &chilled
+    } else {
+        profile
+    };
+    let mut bodies = match counts {
+        Some(counts) => create_suns_by_class(counts, galaxy_radius, profile, recenter, rng),
+        None => create_suns(suns, galaxy_radius, profile, recenter, rng),
+    };
+    if black_holes > 0 {
+        bodies.extend(create_black_holes(black_holes, galaxy_radius, rng));
+        if recenter {
+            zero_total_momentum(&mut bodies);
+        }
+    }
+    bodies
+}
+
+impl MainState {
+    fn new(ctx: &mut Context, settings: Settings, config: SimConfig) -> GameResult<MainState> {
+        graphics::clear(
+            ctx,
+            graphics::Color::from_rgba_u32(settings.theme.background),
+        );
+        let (width, height) = graphics::drawable_size(ctx);
+        info!("seed {}", settings.seed);
+        let (bodies, camera, rng) = seeded_start(&settings, height, &config.profile)?;
+        let (theme, trace_style) = (settings.theme, settings.theme.traces);
+        let trace_interval = settings.trace_interval;
+        let running = camera.running && !settings.paused;
+        let spawner = settings
+            .spawn_rate
+            .map(|rate| ContinuousSpawn::new(rate, settings.fps, settings.max_bodies));
+        let slingshots = SlingshotDetector::new(SLINGSHOT_RANGE, settings.slingshot_threshold);
+        let sound = if settings.sound {
+            ImpactSound::new(ctx)
+        } else {
+            None
+        };
+        let star_sprite = if settings.sprites {
+            Some(graphics::Image::from_bytes(ctx, STAR_SPRITE)?)
+        } else {
+            None
+        };
+        let checkpoints = checkpoints(&settings)?;
+        let meshes = MeshCache::new(settings.circle_quality);
+        let replay = settings
+            .replay
+            .as_deref()
+            .map(|path| Trajectory::load(path, &config.profile))
+            .transpose()?;
+        let bodies = match &replay {
+            Some(replay) => replay.bodies_at(replay.start()),
+            None => bodies,
+        };
+        let sim = simulation(bodies, config, &settings);
+        let camera_path = settings
+            .camera_path
+            .as_deref()
+            .map(CameraPath::load)
+            .transpose()?;
+        let split = match &settings.compare {
+            Some(comparison) => Some(Split::new(ctx, comparison, &sim, &settings)?),
+            None => None,
+        };
+        let mut s = MainState {
+            sim,
+            rng,
+            timestep: FixedTimestep::new(settings.fps),
+            settings,
+            meshes,
+            star_sprite,
+            potential: PotentialField::default(),
+            shake: Shake::default(),
+            flashes: Vec::new(),
+            fading_traces: Vec::new(),
+            spawner,
+            sound,
+            checkpoints,
+            slingshots,
+            annotations: Vec::new(),
+            selected: None,
+            prediction: Vec::new(),
+            prediction_for: None,
+            screen_width: width,
+            screen_height: height,
+            center: camera.center,
+            center_target: camera.center,
+            zoom: camera.zoom,
+            zoom_target: camera.zoom,
+            view_angle: 0.0,
+            speed: camera.speed,
+            running,
+            show_traces: camera.show_traces,
+            color_mode: ColorMode::Class,
+            theme,
+            trace_style,
+            trace_color: match trace_style {
+                TraceStyle::Fixed(color) => color,
+                _ => TRACE_COLOR,
+            },
+            trace_interval,
+            show_hud: true,
+            show_perf: false,
+            show_ids: false,
+            show_tides: false,
+            show_barycenter_lines: false,
+            show_courses: false,
+            show_inspect: false,
+            show_help: false,
+            split,
+            cinematic: false,
+            slowmo: 1.0,
+            show_energy: false,
+            energy: VecDeque::with_capacity(ENERGY_HISTORY),
+            frame_times: VecDeque::with_capacity(PERF_HISTORY),
+            virial: None,
+            shut_down: false,
+            show_minimap: true,
+            show_heaviest: false,
+            show_fastest: false,
+            show_hottest: false,
+            follow: None,
+            camera_path,
+            replay,
+            lock_barycenter: false,
+            barycenter_offset: Point2::ZERO,
+        };
+        s.lock_speed_to_period();
+        Ok(s)
+    }
+
+    /// The view of the main run, which gets the left half of the window in
+    /// a split screen.
+    fn view(&self) -> View {
+        let width = match self.split {
+            Some(_) => self.screen_width / 2.0,
+            None => self.screen_width,
+        };
+        View {
+            screen_width: width,
+            screen_height: self.screen_height,
+            zoom: self.zoom,
+            center: self.center,
+            angle: self.view_angle,
+            tilt: self.settings.tilt.to_radians(),
+        }
+    }
+
+    fn camera(&self) -> CameraState {
+        CameraState {
+            center: self.center_target,
+            zoom: self.zoom_target,
+            speed: self.speed,
+            running: self.running,
+            show_traces: self.show_traces,
+        }
+    }
+
+    /// Clean up right before quitting, only the first call does anything,
+    /// whichever way the window is closed.
+    fn shutdown(&mut self) {
+        if self.shut_down {
+            return;
+        }
+        self.shut_down = true;
+        self.write_output();
+        self.write_svg();
+        if let Some(checkpoints) = &mut self.checkpoints {
+            if let Err(e) = checkpoints.wait() {
+                error!("failed to write checkpoint: {}", e);
+            }
+        }
+    }
+
+    /// Write the final state if asked to.
+    fn write_output(&self) {
+        if let Some(path) = &self.settings.output {
+            match write_json(&self.sim, Some(&self.camera()), path) {
+                Ok(()) => info!("wrote final state to {}", path.display()),
+                Err(e) => error!("failed to write {}: {}", path.display(), e),
+            }
+        }
+    }
+
+    /// Save the traces and bodies as they are colored on screen as SVG, if
+    /// asked to.
+    fn write_svg(&self) {
+        if let Some(path) = &self.settings.svg {
+            let actors = self.sim.actors();
+            let trace_style = self.trace_style;
+            let trace_color = |s: &Actor| trace_style.color(s);
+            match write_svg(actors, trace_color, self.color_mode.painter(actors), path) {
+                Ok(()) => info!("wrote traces to {}", path.display()),
+                Err(e) => error!("failed to write {}: {}", path.display(), e),
+            }
+        }
+    }
+
+    /// Respawn a fresh galaxy and start the clock over, a replay starts over
+    /// from its beginning instead.
+    fn reset(&mut self) {
+        if self.replay.is_some() {
+            return self.restart();
+        }
+        let profile = self.sim.config().profile;
+        let mut bodies = spawn_galaxy(&self.settings, self.screen_height, &profile, &mut self.rng);
+        if self.settings.pin_heaviest {
+            pin_heaviest(&mut bodies);
+        }
+        self.start_over(bodies);
+    }
+
+    /// Go back to the very bodies the game started with, so a run can be
+    /// repeated exactly.
+    fn restart(&mut self) {
+        if let Some(replay) = &self.replay {
+            let bodies = replay.bodies_at(replay.start());
+            return self.start_over(bodies);
+        }
+        let profile = self.sim.config().profile;
+        match seeded_start(&self.settings, self.screen_height, &profile) {
+            Ok((bodies, _, rng)) => {
+                self.rng = rng;
+                self.start_over(bodies);
+            }
+            Err(e) => error!("failed to restart: {}", e),
+        }
+    }
+
+    fn start_over(&mut self, bodies: Vec<Actor>) {
+        let sample_traces = self.sim.samples_traces();
+        self.sim = simulation(bodies, self.sim.config().clone(), &self.settings);
+        self.sim.set_sample_traces(sample_traces);
+        self.meshes = MeshCache::new(self.settings.circle_quality);
+        if let Some(split) = &mut self.split {
+            split.restart(&self.sim, &self.settings);
+        }
+        self.flashes.clear();
+        self.fading_traces.clear();
+        self.slingshots =
+            SlingshotDetector::new(SLINGSHOT_RANGE, self.settings.slingshot_threshold);
+        self.annotations.clear();
+        self.virial = None;
+        self.energy.clear();
+        let settings = &self.settings;
+        self.spawner = settings
+            .spawn_rate
+            .map(|rate| ContinuousSpawn::new(rate, settings.fps, settings.max_bodies));
+        self.lock_speed_to_period();
+    }
+
+    /// Move the bodies `dt` further along the trajectory being replayed. At
+    /// its end the replay starts over or holds still, whichever was asked for.
+    fn advance_replay(&mut self, dt: f32) {
+        let Some(replay) = &self.replay else {
+            return;
+        };
+        let time = self.sim.time().max(replay.start()) + dt;
+        if time <= replay.end() {
+            self.sim.replay(replay.bodies_at(time), time);
+        } else if self.settings.replay_loop {
+            self.restart();
+        } else {
+            let end = replay.end();
+            self.sim.replay(replay.bodies_at(end), end);
+            self.running = false;
+        }
+    }
+
+    /// Pick the speed so the heaviest pair of bodies takes `period_seconds`
+    /// of wall-clock time per orbit, if asked to. Whatever the frame rate, a
+    /// speed of 1 simulates one second per second.
+    fn lock_speed_to_period(&mut self) {
+        let Some(seconds) = self.settings.period_seconds else {
+            return;
+        };
+        match dominant_period(self.sim.actors(), self.sim.config()) {
+            Some(period) => {
+                self.speed = period / seconds;
+                info!("orbit of {:.1} s, speed set to {:.3}x", period, self.speed);
+            }
+            None => warn!("no orbit to time, speed stays at {}x", self.speed),
+        }
+    }
+
+    /// Ease the slow motion factor towards what the closest pair of bodies
+    /// asks for, leaving `speed` to the user.
+    fn update_slowmo(&mut self) {
+        let target = if self.cinematic {
+            slowmo_target(closest_approach(self.sim.actors()))
+        } else {
+            1.0
+        };
+        self.slowmo += (target - self.slowmo) * SLOWMO_SMOOTH;
+    }
+
+    fn sample_energy(&mut self) {
+        let (actors, config) = (self.sim.actors(), self.sim.config());
+        let total = kinetic_energy(actors) + potential_energy(actors, config);
+        if self.energy.len() == ENERGY_HISTORY {
+            self.energy.pop_front();
+        }
+        self.energy.push_back((self.sim.time(), total));
+    }
+
+    fn update_virial(&mut self) {
+        let ratio = virial_ratio(self.sim.actors(), self.sim.config());
+        self.virial = match (self.virial, ratio) {
+            (Some(avg), Some(ratio)) => Some(avg + (ratio - avg) * VIRIAL_SMOOTH),
+            (_, ratio) => ratio,
+        };
+    }
+
+    /// Add a random sun around the center of mass of the others. One of a
+    /// `stream` leaves the momentum of the others alone.
+    fn spawn_sun(&mut self, stream: bool) {
+        let actors = self.sim.actors();
+        let radius = galaxy_radius(actors.len() as u32 + 1, self.screen_height);
+        let profile = &self.sim.config().profile;
+        let sun = create_sun(center_of_mass(actors), radius, profile, &mut self.rng);
+        for sim in self.sims_mut() {
+            if stream {
+                sim.add_keeping_momentum(sun.clone());
+            } else {
+                sim.add(sun.clone());
+            }
+        }
+    }
+
+    /// The main run and the one compared to it, if any. Changes to the
+    /// bodies go to both, so they only differ by their physics.
+    fn sims_mut(&mut self) -> impl Iterator<Item = &mut Simulation> {
+        std::iter::once(&mut self.sim).chain(self.split.as_mut().map(|s| &mut s.sim))
+    }
+
+    /// Spawn a pinned sun under the mouse cursor with a ring of suns
+    /// orbiting it.
+    fn spawn_ring(&mut self, ctx: &Context) {
+        let mouse = ggez::input::mouse::position(ctx);
+        let center = self.view().to_world(Point2::new(mouse.x, mouse.y));
+        let settings = &self.settings;
+        let profile = &self.sim.config().profile;
+        let ring = create_ring(
+            center,
+            settings.ring_mass,
+            settings.ring_count,
+            settings.ring_radius,
+            profile,
+            &mut self.rng,
+        );
+        for sim in self.sims_mut() {
+            sim.add_all(ring.clone());
+        }
+    }
+
+    /// Push the selected body a bit into `direction` on screen, also while
+    /// paused.
+    fn nudge_selected(&mut self, direction: Vector2) {
+        if let Some(id) = self.selected {
+            let direction = Mat2::from_angle(-self.view_angle) * direction;
+            let delta = direction * NUDGE_DELTA / self.zoom_target;
+            for sim in self.sims_mut() {
+                sim.nudge(id, delta);
+            }
+            self.prediction_for = None;
+        }
+    }
+
+    /// Snap the selected body and its strongest neighbor into a circular
+    /// orbit around each other.
+    fn stabilize_selected(&mut self) {
+        if let Some(id) = self.selected {
+            for sim in self.sims_mut() {
+                sim.stabilize(id);
+            }
+            self.prediction_for = None;
+        }
+    }
+
+    fn remove_lightest(&mut self) {
+        if let Some(removed) = self.sim.remove_lightest() {
+            self.meshes.traces.remove(&removed.id);
+        }
+        if let Some(split) = &mut self.split {
+            if let Some(removed) = split.sim.remove_lightest() {
+                split.meshes.traces.remove(&removed.id);
+            }
+        }
+    }
+
+    /// Id of the body under the given screen position, if any.
+    fn body_at(&self, screen_pos: Point2) -> Option<u32> {
+        let pos = self.view().to_world(screen_pos);
+        let margin = SELECTION_MARGIN / self.zoom;
+        self.sim
+            .actors()
+            .iter()
+            .filter(|s| s.pos.distance(pos) <= s.radius + margin)
+            .min_by(|a, b| a.pos.distance(pos).total_cmp(&b.pos.distance(pos)))
+            .map(|s| s.id)
+    }
+
+    /// Select the body under the given screen position, if any.
+    fn select_at(&mut self, screen_pos: Point2) {
+        self.selected = self.body_at(screen_pos);
+    }
+
+    /// Grow the body under the cursor while `.` is held, shrink it while `,`
+    /// is, a bit every frame. Also works while paused.
+    fn paint_mass(&mut self, ctx: &Context) {
+        let grow = keyboard::is_key_pressed(ctx, KeyCode::Period);
+        if grow == keyboard::is_key_pressed(ctx, KeyCode::Comma) {
+            return;
+        }
+        let mouse = ggez::input::mouse::position(ctx);
+        if let Some(id) = self.body_at(Point2::new(mouse.x, mouse.y)) {
+            let rate = if grow {
+                MASS_PAINT_RATE
+            } else {
+                1.0 / MASS_PAINT_RATE
+            };
+            let factor = rate.powf(timer::delta(ctx).as_secs_f32());
+            for sim in self.sims_mut() {
+                sim.scale_mass(id, factor, MASS_PAINT_MIN);
+            }
+            self.prediction_for = None;
+        }
+    }
+
+    /// Move the view by `delta` screen pixels, which stops following a body.
+    /// While locked to the barycenter this moves the view relative to it.
+    fn pan(&mut self, delta: Point2) {
+        self.follow = None;
+        let delta = Mat2::from_angle(-self.view_angle) * delta / self.zoom;
+        self.center_target += delta;
+        self.barycenter_offset += delta;
+    }
+
+    fn toggle_barycenter_lock(&mut self) {
+        self.lock_barycenter = !self.lock_barycenter;
+        self.barycenter_offset = Point2::ZERO;
+        if self.lock_barycenter {
+            self.follow = None;
+        }
+    }
+
+    /// Toggle the highlight of `extreme`, with `follow` toggle following it
+    /// instead.
+    fn toggle_extreme(&mut self, extreme: Extreme, follow: bool) {
+        let shown = match extreme {
+            Extreme::Heaviest => &mut self.show_heaviest,
+            Extreme::Fastest => &mut self.show_fastest,
+            Extreme::Hottest => &mut self.show_hottest,
+        };
+        if follow {
+            if self.follow == Some(extreme) {
+                self.follow = None;
+            } else {
+                self.follow = Some(extreme);
+                self.lock_barycenter = false;
+                *shown = true;
+            }
+        } else {
+            *shown = !*shown;
+        }
+    }
+
+    fn selected_index(&self) -> Option<usize> {
+        let id = self.selected?;
+        self.sim.actors().iter().position(|s| s.id == id)
+    }
+
+    /// Recompute the predicted path of the selected body whenever the selection
+    /// or the speed changed or the simulation advanced noticeably.
+    fn update_prediction(&mut self, dt: f32) {
+        let idx = match self.selected_index() {
+            Some(idx) => idx,
+            None => {
+                self.selected = None;
+                self.prediction.clear();
+                self.prediction_for = None;
+                return;
+            }
+        };
+        let actors = self.sim.actors();
+        let id = actors[idx].id;
+        let fresh = match self.prediction_for {
+            Some((for_id, step, for_dt)) => {
+                for_id == id && for_dt == dt && self.sim.steps() - step < PREDICTION_REFRESH_STEPS
+            }
+            None => false,
+        };
+        if !fresh {
+            let config = self.sim.config();
+            self.prediction = predict_path(actors, idx, PREDICTION_STEPS, dt, config);
+            self.prediction_for = Some((id, self.sim.steps(), dt));
+        }
+    }
+
+    /// Change the zoom target by `factor` while keeping the world position
+    /// under the mouse cursor stationary.
+    fn zoom_at_cursor(&mut self, ctx: &Context, factor: f32) {
+        let mouse = ggez::input::mouse::position(ctx);
+        let mouse = Point2::new(mouse.x, mouse.y);
+        let view = self.view();
+        let to_world = |zoom, center| {
+            View {
+                zoom,
+                center,
+                ..view
+            }
+            .to_world(mouse)
+        };
+        let before = to_world(self.zoom_target, self.center_target);
+        self.zoom_target *= factor;
+        let after = to_world(self.zoom_target, self.center_target);
+        self.center_target += before - after;
+        self.barycenter_offset += before - after;
+    }
+}
+
+impl EventHandler for MainState {
+    fn update(&mut self, ctx: &mut Context) -> GameResult {
+        let fps = self.settings.fps;
+        self.update_slowmo();
+        let dt = self.speed * self.slowmo / (fps as f32);
+        let trace_interval = scaled_trace_interval(self.trace_interval, self.speed * self.slowmo);
+        for sim in self.sims_mut() {
+            sim.set_trace_interval(trace_interval);
+        }
+        for _ in 0..self.timestep.advance(timer::delta(ctx)) {
+            if self.running {
+                if let Some(split) = &mut self.split {
+                    split.sim.step(dt);
+                    for ended in split.sim.ended() {
+                        split.meshes.traces.remove(&ended.id);
+                    }
+                }
+                let collisions = if self.replay.is_some() {
+                    self.advance_replay(dt);
+                    Vec::new()
+                } else {
+                    self.sim.step(dt)
+                };
+                for ended in self.sim.ended() {
+                    self.meshes.traces.remove(&ended.id);
+                    if ended.trace.len() >= 3 {
+                        let trace = FadingTrace::new(ended, self.trace_style);
+                        self.fading_traces.push(trace);
+                    }
+                }
+                if self.settings.events {
+                    print_events(&mut io::stdout().lock(), &self.sim, &collisions)?;
+                }
+                self.flashes.extend(collisions.iter().map(Flash::new));
+                if self.settings.shake {
+                    let view = self.view();
+                    for c in &collisions {
+                        self.shake.hit(c, view);
+                    }
+                }
+                if let Some(sound) = &mut self.sound {
+                    sound.add(&collisions);
+                }
+                let bodies = self.sim.actors().len();
+                for _ in 0..self.spawner.as_mut().map_or(0, |s| s.advance(bodies)) {
+                    self.spawn_sun(true);
+                }
+                if self.sim.steps().is_multiple_of(ENERGY_SAMPLE_STEPS) {
+                    self.sample_energy();
+                }
+                if self.sim.steps().is_multiple_of(fps as u64) {
+                    let actors = self.sim.actors();
+                    let config = self.sim.config();
+                    let (ke, pe) = (kinetic_energy(actors), potential_energy(actors, config));
+                    info!("energy: kinetic {} potential {} total {}", ke, pe, ke + pe);
+                    debug!(
+                        "step {} state hash {:016x}",
+                        self.sim.steps(),
+                        self.sim.state_hash()
+                    );
+                }
+            }
+            // println!("{}", timer::fps(ctx));
+        }
+        if let Some(sound) = &mut self.sound {
+            sound.play(ctx, timer::delta(ctx));
+        }
+        if self.running {
+            let slingshots = self.slingshots.update(self.sim.actors());
+            self.annotations
+                .extend(slingshots.iter().map(Annotation::new));
+            let camera = self.camera();
+            if let Some(checkpoints) = &mut self.checkpoints {
+                if let Err(e) = checkpoints.update(&self.sim, Some(&camera)) {
+                    error!("failed to write checkpoint: {}", e);
+                }
+            }
+        }
+        self.paint_mass(ctx);
+        self.update_prediction(dt);
+        self.update_virial();
+        Ok(())
+    }
+
+    fn draw(&mut self, ctx: &mut Context) -> GameResult {
+        graphics::clear(ctx, graphics::Color::from_rgba_u32(self.theme.background));
+        if let Some((center, zoom)) = self
+            .camera_path
+            .as_ref()
+            .and_then(|p| p.at(self.sim.time()))
+        {
+            // no smoothing, so every keyframe is hit exactly on time
+            (self.center, self.center_target) = (center, center);
+            (self.zoom, self.zoom_target) = (zoom, zoom);
+        } else {
+            self.zoom = zoom_smooth(self.zoom, self.zoom_target, self.settings.zoom_smoothing);
+            if let Some(idx) = self.follow.and_then(|e| e.find(self.sim.actors())) {
+                self.center_target = self.sim.actors()[idx].pos;
+            } else if self.lock_barycenter {
+                self.center_target = center_of_mass(self.sim.actors()) + self.barycenter_offset;
+            }
+            self.center = move_smooth(self.center, self.center_target, self.settings.pan_smoothing);
+        }
+        let mut view = self.view();
+        if self.settings.shake {
+            // the shake stays out of `center`, so it cannot drift the camera
+            view.center += self.shake.next_offset() / view.zoom;
+        }
+        let elapsed = timer::delta(ctx).as_secs_f32();
+        if self.frame_times.len() == PERF_HISTORY {
+            self.frame_times.pop_front();
+        }
+        self.frame_times.push_back(elapsed);
+        for f in &mut self.flashes {
+            f.lifetime -= elapsed;
+        }
+        self.flashes.retain(|f| f.lifetime > 0.0);
+        for t in &mut self.fading_traces {
+            t.lifetime -= elapsed;
+        }
+        self.fading_traces.retain(|t| t.lifetime > 0.0);
+        for a in &mut self.annotations {
+            a.lifetime -= elapsed;
+        }
+        self.annotations.retain(|a| a.lifetime > 0.0);
+        let background = graphics::Color::from_rgba_u32(self.theme.background);
+        if let Some(split) = &self.split {
+            graphics::set_canvas(ctx, Some(&split.left));
+            graphics::set_screen_coordinates(
+                ctx,
+                graphics::Rect::new(0.0, 0.0, view.screen_width, view.screen_height),
+            )?;
+            graphics::clear(ctx, background);
+        }
+        let style = BodyStyle {
+            glow: self.settings.glow,
+            traces: self.show_traces.then_some(self.trace_style),
+            color_mode: self.color_mode,
+            sprite: self.star_sprite.as_ref(),
+        };
+        if self.settings.potential_field {
+            let columns = self.settings.potential_grid;
+            draw_potential(ctx, &mut self.potential, &self.sim, columns, &view)
+                .expect("failed to draw potential field");
+        }
+        if self.show_barycenter_lines {
+            draw_barycenter_lines(ctx, self.sim.actors(), &view)
+                .expect("failed to draw barycenter lines");
+        }
+        if self.show_courses {
+            draw_collision_courses(ctx, self.sim.actors(), &view)
+                .expect("failed to draw collision courses");
+        }
+        if self.show_traces {
+            for t in &self.fading_traces {
+                draw_fading_trace(ctx, t, &view).expect("failed to draw fading trace");
+            }
+        }
+        draw_bodies(ctx, &mut self.meshes, self.sim.actors(), &style, &view)
+            .expect("failed to draw suns");
+        if let Some(idx) = self.selected_index() {
+            draw_prediction(ctx, &self.prediction, &view).expect("failed to draw predicted path");
+            let selected = &self.sim.actors()[idx];
+            draw_highlight(ctx, selected, SELECTION_COLOR, &view)
+                .expect("failed to draw selection");
+            if self.show_tides {
+                if let Some(n) = dominant_neighbor(self.sim.actors(), idx) {
+                    let neighbor = &self.sim.actors()[n];
+                    let g = self.sim.config().profile.g;
+                    draw_tides(ctx, selected, neighbor, g, &view).expect("failed to draw tides");
+                }
+            }
+        }
+        let shown = [
+            (Extreme::Heaviest, self.show_heaviest),
+            (Extreme::Fastest, self.show_fastest),
+            (Extreme::Hottest, self.show_hottest),
+        ];
+        for (extreme, _) in shown.into_iter().filter(|(_, show)| *show) {
+            if let Some(idx) = extreme.find(self.sim.actors()) {
+                let actor = &self.sim.actors()[idx];
+                draw_highlight(ctx, actor, extreme.color(), &view)
+                    .expect("failed to draw highlight");
+            }
+        }
+        for f in &self.flashes {
+            draw_flash(ctx, f, &view).expect("failed to draw flash");
+        }
+        for a in &self.annotations {
+            draw_annotation(ctx, a, &view).expect("failed to draw annotation");
+        }
+        if self.show_ids {
+            draw_ids(ctx, self.sim.actors(), &view).expect("failed to draw ids");
+        }
+        if let Some(split) = &mut self.split {
+            graphics::set_canvas(ctx, Some(&split.right));
+            graphics::clear(ctx, background);
+            draw_bodies(ctx, &mut split.meshes, split.sim.actors(), &style, &view)
+                .expect("failed to draw compared suns");
+            graphics::set_canvas(ctx, None);
+            let screen = graphics::Rect::new(0.0, 0.0, self.screen_width, self.screen_height);
+            graphics::set_screen_coordinates(ctx, screen)?;
+            draw_split(ctx, split, &self.sim, screen).expect("failed to draw split screen");
+        }
+        if self.show_inspect {
+            draw_inspect(ctx, self).expect("failed to draw nearest bodies");
+        }
+        if self.show_minimap {
+            draw_minimap(ctx, self).expect("failed to draw minimap");
+        }
+        if self.show_energy {
+            draw_energy(ctx, self).expect("failed to draw energy plot");
+        }
+        if self.show_hud {
+            draw_hud(ctx, self).expect("failed to draw hud");
+        }
+        if self.show_perf {
+            draw_perf(ctx, self).expect("failed to draw perf overlay");
+        }
+        if self.show_help {
+            draw_help(ctx, self).expect("failed to draw help");
+        }
+        graphics::present(ctx)?;
+        timer::yield_now();
+        Ok(())
+    }
+
+    fn key_down_event(
+        &mut self,
+        ctx: &mut Context,
+        keycode: KeyCode,
+        keymod: KeyMods,
+        _repeat: bool,
     ) {
+        if self.show_help {
+            self.show_help = false;
+            return;
+        }
         match keycode {
-            KeyCode::Escape | KeyCode::Q => event::quit(ctx),
+            KeyCode::Escape => {
+                self.shutdown();
+                event::quit(ctx);
+            }
             KeyCode::Space => self.running = !self.running,
             KeyCode::Plus => self.speed *= SPEED_FACTOR,
             KeyCode::Minus => self.speed /= SPEED_FACTOR,
-            KeyCode::I => self.zoom_target *= ZOOM_FACTOR,
-            KeyCode::O => self.zoom_target /= ZOOM_FACTOR,
-            KeyCode::A => self.center_target.x -= MOVE_DELTA / self.zoom,
-            KeyCode::D => self.center_target.x += MOVE_DELTA / self.zoom,
-            KeyCode::S => self.center_target.y -= MOVE_DELTA / self.zoom,
-            KeyCode::W => self.center_target.y += MOVE_DELTA / self.zoom,
+            KeyCode::I => self.zoom_at_cursor(ctx, ZOOM_FACTOR),
+            KeyCode::O => self.zoom_at_cursor(ctx, 1.0 / ZOOM_FACTOR),
+            KeyCode::A => self.pan(Point2::new(-MOVE_DELTA, 0.0)),
+            KeyCode::D => self.pan(Point2::new(MOVE_DELTA, 0.0)),
+            KeyCode::S => self.pan(Point2::new(0.0, -MOVE_DELTA)),
+            KeyCode::W => self.pan(Point2::new(0.0, MOVE_DELTA)),
+            KeyCode::T if keymod.contains(KeyMods::SHIFT) => {
+                let sample = !self.sim.samples_traces();
+                for sim in self.sims_mut() {
+                    sim.set_sample_traces(sample);
+                }
+            }
             KeyCode::T => self.show_traces = !self.show_traces,
+            KeyCode::V => {
+                self.color_mode = match self.color_mode {
+                    ColorMode::Class => ColorMode::Speed,
+                    ColorMode::Speed => ColorMode::Class,
+                }
+            }
+            KeyCode::LBracket => self.trace_interval = (self.trace_interval / 2).max(1),
+            KeyCode::RBracket => self.trace_interval = self.trace_interval.saturating_mul(2),
+            KeyCode::L => self.trace_style = self.trace_style.next(self.trace_color),
+            KeyCode::H => self.show_hud = !self.show_hud,
+            KeyCode::F4 => self.show_energy = !self.show_energy,
+            KeyCode::Q => self.view_angle += ROTATE_STEP,
+            KeyCode::E => self.view_angle -= ROTATE_STEP,
+            KeyCode::F2 => self.show_ids = !self.show_ids,
+            KeyCode::F3 => self.show_perf = !self.show_perf,
+            KeyCode::F5 => self.write_svg(),
+            KeyCode::F1 => self.show_help = true,
+            KeyCode::Slash if keymod.contains(KeyMods::SHIFT) => self.show_help = true,
+            KeyCode::G => self.show_tides = !self.show_tides,
+            KeyCode::X => self.show_barycenter_lines = !self.show_barycenter_lines,
+            KeyCode::F6 => self.show_courses = !self.show_courses,
+            KeyCode::U => self.show_inspect = !self.show_inspect,
+            KeyCode::Z => self.cinematic = !self.cinematic,
+            KeyCode::M => self.show_minimap = !self.show_minimap,
+            KeyCode::P => {
+                if let Some(id) = self.selected {
+                    for sim in self.sims_mut() {
+                        sim.toggle_pinned(id);
+                    }
+                    self.prediction_for = None;
+                }
+            }
+            KeyCode::Y => self.stabilize_selected(),
+            KeyCode::Up => self.nudge_selected(Vector2::Y),
+            KeyCode::Down => self.nudge_selected(-Vector2::Y),
+            KeyCode::Left => self.nudge_selected(-Vector2::X),
+            KeyCode::Right => self.nudge_selected(Vector2::X),
+            KeyCode::R if keymod.contains(KeyMods::SHIFT) => self.restart(),
+            KeyCode::R => self.reset(),
+            KeyCode::C => self.toggle_barycenter_lock(),
+            KeyCode::J => self.spawn_sun(false),
+            KeyCode::N => self.spawn_ring(ctx),
+            KeyCode::K => self.remove_lightest(),
+            KeyCode::B => self.toggle_extreme(Extreme::Heaviest, keymod.contains(KeyMods::SHIFT)),
+            KeyCode::F => self.toggle_extreme(Extreme::Fastest, keymod.contains(KeyMods::SHIFT)),
+            KeyCode::F7 => self.toggle_extreme(Extreme::Hottest, keymod.contains(KeyMods::SHIFT)),
             _ => (), //all other events are unhandled
         }
     }
+
+    fn resize_event(&mut self, ctx: &mut Context, width: f32, height: f32) {
+        self.screen_width = width;
+        self.screen_height = height;
+        let screen = graphics::Rect::new(0.0, 0.0, width, height);
+        if let Err(e) = graphics::set_screen_coordinates(ctx, screen) {
+            warn!("failed to resize the view: {}", e);
+        }
+        if let Some(split) = &mut self.split {
+            match half_canvases(ctx) {
+                Ok((left, right)) => {
+                    split.left = left;
+                    split.right = right;
+                }
+                Err(e) => warn!("failed to resize the split screen: {}", e),
+            }
+        }
+    }
+
+    fn quit_event(&mut self, _ctx: &mut Context) -> bool {
+        self.shutdown();
+        false
+    }
+
+    fn mouse_button_down_event(&mut self, _ctx: &mut Context, button: MouseButton, x: f32, y: f32) {
+        if button == MouseButton::Left {
+            self.select_at(Point2::new(x, y));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use assert_approx_eq::assert_approx_eq;
+    use nbody::galaxy::new_sun;
+
+    #[test]
+    fn test_screen_world_round_trip() {
+        let points = [
+            Point2::new(0.0, 0.0),
+            Point2::new(123.4, -567.8),
+            Point2::new(-1_000.0, 2_500.0),
+        ];
+        let cameras = [
+            (1.0, Point2::ZERO, 0.0),
+            (0.1, Point2::new(50.0, -20.0), 0.0),
+            (7.5, Point2::new(-300.0, 400.0), 0.0),
+            (1.0, Point2::new(10.0, 10.0), 0.7),
+            (0.3, Point2::new(-80.0, 5.0), -2.5),
+        ];
+        for p in points {
+            for (zoom, center, angle) in cameras {
+                let view = View {
+                    screen_width: SCREEN_W,
+                    screen_height: SCREEN_H,
+                    zoom,
+                    center,
+                    angle,
+                    tilt: angle.abs() / 4.0,
+                };
+                let world = view.to_world(view.to_screen(p));
+                assert_approx_eq!(world.x, p.x, 1e-2);
+                assert_approx_eq!(world.y, p.y, 1e-2);
+            }
+        }
+    }
+
+    #[test]
+    fn test_rotated_view() {
+        let view = View {
+            screen_width: SCREEN_W,
+            screen_height: SCREEN_H,
+            zoom: 2.0,
+            center: Point2::new(100.0, 0.0),
+            angle: std::f32::consts::FRAC_PI_2,
+            tilt: 0.0,
+        };
+        let middle = Point2::new(SCREEN_W / 2.0, SCREEN_H / 2.0);
+        assert_eq!(view.to_screen(view.center), middle);
+        // A quarter turn counterclockwise puts what was right of the center
+        // above it, and screen y points down.
+        let right = view.to_screen(Point2::new(110.0, 0.0));
+        assert_approx_eq!(right.x, middle.x, 1e-3);
+        assert_approx_eq!(right.y, middle.y - 20.0, 1e-3);
+        let dir = view.to_screen_direction(Vector2::X);
+        assert_approx_eq!(dir.x, 0.0, 1e-6);
+        assert_approx_eq!(dir.y, -1.0, 1e-6);
+    }
+
+    #[test]
+    fn test_tilted_view() {
+        let flat = View {
+            screen_width: SCREEN_W,
+            screen_height: SCREEN_H,
+            zoom: 1.5,
+            center: Point2::new(30.0, -10.0),
+            angle: 0.4,
+            tilt: 0.0,
+        };
+        let tilted = View {
+            tilt: 60f32.to_radians(),
+            ..flat
+        };
+        let middle = Point2::new(SCREEN_W / 2.0, SCREEN_H / 2.0);
+        // worked out by hand: squashed to half at 60 degrees, then sunk by
+        // TILT_DEPTH_OFFSET times sin(60°) of that towards the middle
+        let expected = [
+            (Point2::new(130.0, 40.0), Point2::new(708.9528, 344.5347)),
+            (Point2::new(-70.0, -300.0), Point2::new(631.2378, 599.7194)),
+        ];
+        for (p, screen) in expected {
+            // no tilt is the plain rotation, scaling and flip, to the bit
+            let rotated = Mat2::from_angle(flat.angle) * (p - flat.center);
+            let plain = Point2::new(rotated.x * 1.5, -rotated.y * 1.5) + middle;
+            assert_eq!(flat.to_screen(p), plain);
+            let (a, b) = (flat.to_screen(p), tilted.to_screen(p));
+            assert!(b.distance(screen) < 1e-2, "{} vs {}", b, screen);
+            assert_approx_eq!(b.x, a.x, 1e-3);
+            assert!((b.y - middle.y).abs() < (a.y - middle.y).abs() / 2.0);
+            // and back again to where it came from
+            assert!(
+                tilted.to_world(b).distance(p) < 1e-3,
+                "{}",
+                tilted.to_world(b)
+            );
+            // meshes in world coordinates end up at the same spot
+            let m = tilted.transform() * p.extend(0.0).extend(1.0);
+            assert_approx_eq!(m.x, b.x, 1e-3);
+            assert_approx_eq!(m.y, b.y, 1e-2);
+        }
+    }
+
+    #[test]
+    fn test_camera_smoothing() {
+        let (from, to) = (Point2::new(-40.0, 12.5), Point2::new(300.0, -7.0));
+        assert_eq!(zoom_smooth(0.3, 2.5, 1.0), 2.5);
+        assert_eq!(move_smooth(from, to, 1.0), to);
+        // the default glides a tenth of the way
+        assert_approx_eq!(zoom_smooth(1.0, 2.0, 0.1), 1.1);
+        assert!(move_smooth(from, to, 0.1).distance(Point2::new(-6.0, 10.55)) < 1e-4);
+    }
+
+    #[test]
+    fn test_id_labels_skip_small_hidden_and_crowded() {
+        let profile = PhysicsProfile::ARCADE;
+        let body = |id, x, mass| new_sun(id, Point2::new(x, 0.0), Vector2::ZERO, mass, &profile);
+        let actors = [
+            body(1, 0.0, 50.0),
+            // right next to the heavier one, its label would cover the other
+            body(2, 1.0, 10.0),
+            body(3, -200.0, 10.0),
+            body(4, 100_000.0, 60.0),
+            body(5, 200.0, 0.3),
+        ];
+        let view = View {
+            screen_width: SCREEN_W,
+            screen_height: SCREEN_H,
+            zoom: 1.0,
+            center: Point2::ZERO,
+            angle: 0.0,
+            tilt: 0.0,
+        };
+        let ids: Vec<u32> = id_labels(&actors, view).iter().map(|l| l.0).collect();
+        assert_eq!(ids, [1, 3, 5]);
+        let zoomed_out = View { zoom: 0.2, ..view };
+        let ids: Vec<u32> = id_labels(&actors, zoomed_out).iter().map(|l| l.0).collect();
+        assert_eq!(ids, [1]);
+    }
+
+    #[test]
+    fn test_barycenter_lines_fade_with_mass() {
+        let profile = PhysicsProfile::ARCADE;
+        let body = |id, mass| {
+            new_sun(
+                id,
+                Point2::new(id as f32, 0.0),
+                Vector2::ZERO,
+                mass,
+                &profile,
+            )
+        };
+        let mut actors = vec![body(1, 0.3), body(2, 60.0), body(3, 15.0), body(4, 0.001)];
+        let lines = barycenter_lines(&actors);
+        let order: Vec<usize> = lines.iter().map(|l| l.0).collect();
+        assert_eq!(order, [1, 2, 0]);
+        assert_approx_eq!(lines[0].1, BARYCENTER_LINE_ALPHA);
+        assert_approx_eq!(lines[1].1, BARYCENTER_LINE_ALPHA / 2.0);
+
+        actors.extend((10..200).map(|id| body(id, 1.0)));
+        assert_eq!(barycenter_lines(&actors).len(), BARYCENTER_LINES_MAX);
+        assert!(barycenter_lines(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_seeded_start_repeats() {
+        let settings = Config {
+            suns: Some(8),
+            black_holes: Some(1),
+            seed: Some(3),
+            ..Config::default()
+        }
+        .settings();
+        let profile = PhysicsProfile::ARCADE;
+        let state = |bodies: &[Actor]| -> Vec<(u32, Point2, Vector2, f32)> {
+            bodies
+                .iter()
+                .map(|a| (a.id, a.pos, a.velocity(), a.mass()))
+                .collect()
+        };
+        let (first, _, mut first_rng) = seeded_start(&settings, SCREEN_H, &profile).unwrap();
+        let (again, _, mut again_rng) = seeded_start(&settings, SCREEN_H, &profile).unwrap();
+        assert_eq!(first.len(), 9);
+        assert_eq!(state(&first), state(&again));
+        assert_eq!(first_rng.gen::<u64>(), again_rng.gen::<u64>());
+    }
+
+    #[test]
+    fn test_split_runs_differ_only_by_config() {
+        let settings = Config {
+            suns: Some(12),
+            seed: Some(5),
+            ..Config::default()
+        }
+        .settings();
+        let config = SimConfig::default();
+        let (bodies, _, _) = seeded_start(&settings, SCREEN_H, &config.profile).unwrap();
+        let run = |config: &SimConfig| {
+            let mut sim = Simulation::new(bodies.clone(), config.clone());
+            let start = total_energy(&sim);
+            for _ in 0..200 {
+                sim.step(0.01);
+            }
+            (sim.state_hash(), energy_drift(&sim, start))
+        };
+        let (base, drift) = run(&config);
+        assert_eq!(run(&config), (base, drift));
+        assert!(drift.is_finite());
+        let steeper = SimConfig {
+            force_exponent: 2.5,
+            ..config.clone()
+        };
+        assert_ne!(run(&steeper).0, base);
+    }
+
+    #[test]
+    fn test_pin_heaviest() {
+        let settings = Config {
+            suns: Some(6),
+            seed: Some(11),
+            pin_heaviest: Some(true),
+            ..Config::default()
+        }
+        .settings();
+        let config = SimConfig::default();
+        let (bodies, _, _) = seeded_start(&settings, SCREEN_H, &config.profile).unwrap();
+        let heaviest = most_massive(&bodies).unwrap();
+        assert_eq!(bodies.iter().filter(|b| b.pinned).count(), 1);
+        assert!(bodies[heaviest].pinned);
+        let id = bodies[heaviest].id;
+        let mut sim = Simulation::new(bodies.clone(), config);
+        for _ in 0..100 {
+            sim.step(0.01);
+        }
+        for (before, after) in bodies.iter().zip(sim.actors()) {
+            assert_eq!(after.pos == before.pos, after.id == id, "{}", after.id);
+        }
+    }
+
+    #[test]
+    fn test_cold_cloud_collapses() {
+        let start = |cold| {
+            let config = Config {
+                suns: Some(100),
+                seed: Some(8),
+                galaxy_radius: Some(400.0),
+                cold: Some(cold),
+                ..Config::default()
+            };
+            let sim_config = config.sim_config();
+            let (bodies, _, _) =
+                seeded_start(&config.settings(), SCREEN_H, &sim_config.profile).unwrap();
+            Simulation::new(bodies, sim_config)
+        };
+        let mut sim = start(true);
+        let warm = start(false);
+        // only the first cloud is cold, suns spawned later move as usual
+        assert_eq!(sim.config().profile, warm.config().profile);
+        let dispersion = velocity_dispersion(sim.actors());
+        assert!(dispersion < 0.02 * velocity_dispersion(warm.actors()));
+        assert!(dispersion < 1.0, "{}", dispersion);
+        let momentum: Vector2 = sim
+            .actors()
+            .iter()
+            .fold(Vector2::ZERO, |p, a| p + a.velocity() * a.mass());
+        assert!(momentum.length() < 1e-3, "{}", momentum);
+
+        // mass within a fixed radius around the center of mass
+        let core_mass = |actors: &[Actor]| -> f32 {
+            let center = center_of_mass(actors);
+            actors
+                .iter()
+                .filter(|a| a.pos.distance(center) < 100.0)
+                .map(Actor::mass)
+                .sum()
+        };
+        let before = core_mass(sim.actors());
+        for _ in 0..60 {
+            sim.step(1.0 / 60.0);
+        }
+        let after = core_mass(sim.actors());
+        assert!(after > 2.0 * before, "{} -> {}", before, after);
+    }
+
+    #[test]
+    fn test_potential_depths_deepest_at_body() {
+        let view = View {
+            screen_width: 400.0,
+            screen_height: 300.0,
+            zoom: 1.0,
+            center: Point2::ZERO,
+            angle: 0.0,
+            tilt: 0.0,
+        };
+        let config = SimConfig::default();
+        let (rows, depths) = potential_depths(&[], &config, view, 8);
+        assert_eq!((rows, depths.len()), (6, 48));
+        assert!(depths.iter().all(|d| *d == 0.0));
+
+        let body = new_sun(
+            1,
+            Point2::new(125.0, 75.0),
+            Vector2::ZERO,
+            10.0,
+            &config.profile,
+        );
+        let (_, depths) = potential_depths(&[body], &config, view, 8);
+        // the body sits in the middle of column 6, row 1
+        assert_eq!(depths[8 + 6], 1.0);
+        assert!(depths.iter().all(|d| (0.0..=1.0).contains(d)));
+        assert!(depths[40] < 0.1 && depths[8 + 5] > depths[8 + 4]);
+    }
+
+    #[test]
+    fn test_shake_only_for_hard_hits_in_view() {
+        let view = View {
+            screen_width: 400.0,
+            screen_height: 300.0,
+            zoom: 1.0,
+            center: Point2::ZERO,
+            angle: 0.0,
+            tilt: 0.0,
+        };
+        let hit = |x, energy| CollisionEvent {
+            kind: CollisionKind::Bounce,
+            ids: (1, 2),
+            pos: Point2::new(x, 0.0),
+            speed: 1.0,
+            energy,
+            survivor: None,
+        };
+        let mut shake = Shake::default();
+        shake.hit(&hit(0.0, 10.0), view);
+        shake.hit(&hit(1000.0, 1e9), view);
+        assert_eq!(shake.next_offset(), Vector2::ZERO);
+
+        shake.hit(&hit(0.0, 1e9), view);
+        let first = shake.next_offset().length();
+        assert_approx_eq!(first, SHAKE_MAX, 1e-3);
+        let offsets: Vec<f32> = (0..100).map(|_| shake.next_offset().length()).collect();
+        assert!(offsets.windows(2).all(|w| w[1] <= w[0]));
+        assert!(offsets[0] < first && offsets[99] == 0.0);
+    }
+
+    #[test]
+    fn test_nearest_bodies() {
+        let profile = PhysicsProfile::ARCADE;
+        let body = |id, x| new_sun(id, Point2::new(x, 0.0), Vector2::ZERO, 1.0, &profile);
+        let actors = [body(1, -50.0), body(2, 10.0), body(3, 300.0), body(4, -5.0)];
+        assert_eq!(nearest_bodies(&actors, Point2::ZERO, 3), [3, 1, 0]);
+        assert_eq!(nearest_bodies(&actors, Point2::new(250.0, 0.0), 1), [2]);
+        assert_eq!(nearest_bodies(&actors, Point2::ZERO, 10).len(), 4);
+        assert!(nearest_bodies(&[], Point2::ZERO, 3).is_empty());
+
+        // the cursor is turned into world coordinates along with the view
+        let view = View {
+            screen_width: 400.0,
+            screen_height: 300.0,
+            zoom: 2.0,
+            center: Point2::ZERO,
+            angle: std::f32::consts::PI,
+            tilt: 0.0,
+        };
+        // left of the screen center is right of it in the world
+        let cursor = view.to_world(Point2::new(180.0, 150.0));
+        assert_eq!(nearest_bodies(&actors, cursor, 1), [1]);
+    }
+
+    #[test]
+    fn test_higher_fps_steps_more_often() {
+        let steps_in_one_second = |fps| {
+            let mut timestep = FixedTimestep::new(fps);
+            (0..100)
+                .map(|_| timestep.advance(Duration::from_millis(10)))
+                .sum::<u32>()
+        };
+        assert_eq!(steps_in_one_second(30), 30);
+        assert_eq!(steps_in_one_second(60), 60);
+        assert_eq!(steps_in_one_second(240), 240);
+    }
+
+    #[test]
+    fn test_minimap_view_shows_all_bodies() {
+        let points = [
+            Point2::new(-5_000.0, 20.0),
+            Point2::new(300.0, 12_000.0),
+            Point2::new(40.0, -80.0),
+        ];
+        let (center, zoom) = minimap_view(points.iter().copied());
+        for p in points {
+            let m = world_to_screen_coords(p, MINIMAP_SIZE, MINIMAP_SIZE, zoom, center, 0.0, 0.0);
+            assert!((0.0..=MINIMAP_SIZE).contains(&m.x), "{} outside", m);
+            assert!((0.0..=MINIMAP_SIZE).contains(&m.y), "{} outside", m);
+        }
+    }
+
+    #[test]
+    fn test_speed_color_endpoints() {
+        assert_eq!(speed_color(0.0, 50.0), 0x0000_ffff);
+        assert_eq!(speed_color(50.0, 50.0), 0xff00_00ff);
+        assert_eq!(speed_color(25.0, 50.0), 0x00ff_00ff);
+        assert_eq!(speed_color(3.0, 0.0), 0x0000_ffff);
+    }
+
+    #[test]
+    fn test_rainbow_traces_are_stable() {
+        let actor = |id| {
+            let mut rng = StdRng::seed_from_u64(1);
+            let mut sun = create_sun(Point2::ZERO, 10.0, &PhysicsProfile::ARCADE, &mut rng);
+            sun.id = id;
+            sun
+        };
+        let color = |id| TraceStyle::Rainbow.color(&actor(id));
+        assert_eq!(color(7), color(7));
+        assert_ne!(color(7), color(8));
+        assert_eq!(color(7) & 0xff, 0xff);
+        assert_eq!(TraceStyle::MatchBody.color(&actor(7)), actor(7).color);
+    }
+
+    #[test]
+    fn test_parse_trace_style() {
+        assert_eq!("body".parse(), Ok(TraceStyle::MatchBody));
+        assert_eq!("rainbow".parse(), Ok(TraceStyle::Rainbow));
+        assert_eq!("ffffff60".parse(), Ok(TraceStyle::Fixed(0xffff_ff60)));
+        assert_eq!("#ff8000".parse(), Ok(TraceStyle::Fixed(0xff80_00ff)));
+        assert!("zebra".parse::<TraceStyle>().is_err());
+        assert!("ggffff".parse::<TraceStyle>().is_err());
+    }
+
+    #[test]
+    fn test_energy_plot_spans_panel() {
+        assert!(energy_plot(&VecDeque::new(), 100.0, 50.0).is_none());
+        let samples = VecDeque::from(vec![(1.0, -20.0), (2.0, -10.0), (3.0, -15.0)]);
+        let (points, min, max) = energy_plot(&samples, 100.0, 50.0).unwrap();
+        assert_eq!((min, max), (-20.0, -10.0));
+        assert_eq!(points[0], Point2::new(0.0, 50.0));
+        assert_eq!(points[1], Point2::new(50.0, 0.0));
+        assert_eq!(points[2], Point2::new(100.0, 25.0));
+        let flat = VecDeque::from(vec![(1.0, 3.0)]);
+        let (points, _, _) = energy_plot(&flat, 100.0, 50.0).unwrap();
+        assert!(points[0].is_finite());
+    }
+
+    #[test]
+    fn test_slowmo_target() {
+        assert_eq!(slowmo_target(None), 1.0);
+        assert_eq!(slowmo_target(Some(SLOWMO_RANGE * 2.0)), 1.0);
+        assert_eq!(slowmo_target(Some(SLOWMO_RANGE)), 1.0);
+        assert_eq!(slowmo_target(Some(1.0)), SLOWMO_MIN);
+        assert_eq!(slowmo_target(Some(0.5)), SLOWMO_MIN);
+        let halfway = slowmo_target(Some((1.0 + SLOWMO_RANGE) / 2.0));
+        assert_approx_eq!(halfway, (1.0 + SLOWMO_MIN) / 2.0);
+    }
+
+    #[test]
+    fn test_drift_survives_adding_bodies() {
+        let config = Config {
+            suns: Some(20),
+            seed: Some(3),
+            recenter_momentum: Some(false),
+            ..Config::default()
+        };
+        let settings = config.settings();
+        let sim_config = config.sim_config();
+        let (bodies, _, mut rng) = seeded_start(&settings, SCREEN_H, &sim_config.profile).unwrap();
+        let newcomer = create_sun(Point2::ZERO, 500.0, &sim_config.profile, &mut rng);
+        let mut sim = simulation(bodies, sim_config, &settings);
+        let momentum = |sim: &Simulation| -> Vector2 {
+            sim.actors()
+                .iter()
+                .fold(Vector2::ZERO, |p, a| p + a.velocity() * a.mass())
+        };
+        let drift = momentum(&sim);
+        assert!(drift.length() > 1.0, "{}", drift);
+        let expected = drift + newcomer.velocity() * newcomer.mass();
+        sim.add(newcomer);
+        assert!(momentum(&sim).distance(expected) < 1e-3);
+    }
+
+    #[test]
+    fn test_continuous_spawn_stops_at_cap() {
+        let mut spawner = ContinuousSpawn::new(30.0, 60, 5);
+        let spawned: Vec<u32> = (0..4).map(|_| spawner.advance(3)).collect();
+        assert_eq!(spawned, [0, 1, 0, 1]);
+        assert_eq!(ContinuousSpawn::new(600.0, 60, 5).advance(3), 2);
+        for _ in 0..10 {
+            assert_eq!(spawner.advance(5), 0);
+        }
+        assert_eq!(spawner.advance(4), 0);
+        assert_eq!(spawner.advance(4), 1);
+    }
+
+    #[test]
+    fn test_continuous_spawn_ids_stay_unique() {
+        let profile = PhysicsProfile::ARCADE;
+        let mut rng = StdRng::seed_from_u64(5);
+        let mut sim = Simulation::new(Vec::new(), SimConfig::default());
+        let mut spawner = ContinuousSpawn::new(6000.0, 60, 3000);
+        for _ in 0..100 {
+            for _ in 0..spawner.advance(sim.actors().len()) {
+                let mut sun = create_sun(Point2::ZERO, 500.0, &profile, &mut rng);
+                // Force clashes, random ids alone hardly ever collide.
+                sun.id %= 1 << 16;
+                sim.add_keeping_momentum(sun);
+            }
+        }
+        let mut ids: Vec<u32> = sim.actors().iter().map(|a| a.id).collect();
+        assert_eq!(ids.len(), 3000);
+        ids.sort_unstable();
+        ids.dedup();
+        assert_eq!(ids.len(), 3000);
+    }
+
+    #[test]
+    fn test_circle_segments_stay_bounded() {
+        // sides of the polygon a circle is drawn with for a tolerance
+        let segments = |radius: f32, quality| {
+            let tolerance = circle_tolerance(radius, quality);
+            (std::f32::consts::PI / (1.0 - tolerance / radius).acos()).round()
+        };
+        for radius in [0.5, 3.0, 40.0, 500.0] {
+            for zoom in [1e-4, 1e-2, 0.3, 1.0, 7.0, 1e2, 1e4] {
+                let on_screen = (radius * zoom + 1.0_f32).round().max(1.0);
+                let mut last = 0.0;
+                for quality in [0.1, 1.0, 10.0] {
+                    let n = segments(on_screen, quality);
+                    assert!(
+                        (MIN_CIRCLE_SEGMENTS..=MAX_CIRCLE_SEGMENTS).contains(&n),
+                        "{}",
+                        n
+                    );
+                    assert!(n >= last);
+                    last = n;
+                }
+            }
+        }
+        assert_eq!(segments(1.0, 1.0), MIN_CIRCLE_SEGMENTS);
+        assert_eq!(segments(1e6, 1.0), MAX_CIRCLE_SEGMENTS);
+        assert!(segments(20.0, 1.0) > MIN_CIRCLE_SEGMENTS);
+    }
+
+    #[test]
+    fn test_keybindings_match_readme() {
+        let readme = include_str!("../README.md");
+        let table: Vec<(&str, &str)> = readme
+            .split("## keybindings")
+            .nth(1)
+            .expect("no keybindings in the README")
+            .lines()
+            .skip_while(|l| !l.starts_with('|'))
+            .take_while(|l| l.starts_with('|'))
+            .skip(2)
+            .map(|l| {
+                let cells: Vec<&str> = l.split('|').map(str::trim).collect();
+                (cells[1], cells[2])
+            })
+            .collect();
+        let bindings: Vec<(&str, &str)> = KEYBINDINGS.iter().map(|b| (b.keys, b.action)).collect();
+        assert_eq!(table, bindings);
+        let traces = &KEYBINDINGS[7];
+        assert_eq!(
+            help_line(traces, Some(true)),
+            "t        toggle body traces [on]"
+        );
+        assert_eq!(
+            help_line(&KEYBINDINGS[0], None),
+            "w/s/a/d  move up/down/left/right"
+        );
+    }
+
+    #[test]
+    fn test_glow_grows_with_mass() {
+        assert!(glow_factor(0.3) > 1.0);
+        assert!(glow_factor(60.0) > glow_factor(1.0));
+        assert!(glow_factor(300.0) < 2.0 * glow_factor(0.3) + 1.0);
+    }
+
+    #[test]
+    fn test_sprite_matches_mesh_circle() {
+        let profile = PhysicsProfile::ARCADE;
+        let sun = new_sun(1, Point2::new(30.0, -10.0), Vector2::ZERO, 2.0, &profile);
+        let view = View {
+            screen_width: SCREEN_W,
+            screen_height: SCREEN_H,
+            zoom: 3.0,
+            center: Point2::ZERO,
+            angle: 0.0,
+            tilt: 0.0,
+        };
+        let param = sprite_param(&sun, sun.color, &view);
+        // the sprite is white, so the tint is the color it ends up in
+        assert_eq!(param.color, graphics::Color::from_rgba_u32(sun.color));
+        let radius = (sun.radius * view.zoom + 1.0).round();
+        match param.trans {
+            graphics::Transform::Values {
+                dest,
+                scale,
+                offset,
+                ..
+            } => {
+                assert_eq!(Point2::from(dest), view.to_screen(sun.pos));
+                assert_approx_eq!(scale.x * STAR_SPRITE_CORE, radius);
+                assert_eq!(scale.x, scale.y);
+                assert_eq!(Point2::from(offset), Point2::splat(0.5));
+            }
+            _ => panic!("sprite placed by a matrix"),
+        }
+    }
+
+    #[test]
+    fn test_scaled_trace_interval() {
+        assert_eq!(scaled_trace_interval(10, 1.0), 10);
+        assert_eq!(scaled_trace_interval(10, 2.0), 5);
+        assert_eq!(scaled_trace_interval(10, 0.25), 40);
+        assert_eq!(scaled_trace_interval(10, 64.0), 1);
+        assert_eq!(scaled_trace_interval(1, 0.5), 2);
+    }
+
+    #[test]
+    fn test_bench_output_keys() {
+        let settings = Config {
+            suns: Some(5),
+            seed: Some(1),
+            ..Config::default()
+        }
+        .settings();
+        let mut out = Vec::new();
+        run_bench(settings, SimConfig::default(), 10, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let keys: Vec<&str> = out.lines().filter_map(|l| l.split('=').next()).collect();
+        let expected = [
+            "bodies",
+            "final_bodies",
+            "steps",
+            "wall_time_s",
+            "steps_per_second",
+            "final_energy",
+        ];
+        assert_eq!(keys, expected);
+        assert!(out.contains("bodies=5\n") && out.contains("steps=10\n"));
+    }
+
+    #[test]
+    fn test_event_line() {
+        let event = CollisionEvent {
+            kind: CollisionKind::Merge,
+            ids: (42, 77),
+            pos: Point2::ZERO,
+            speed: 3.214,
+            energy: 1.0,
+            survivor: Some(77),
+        };
+        assert_eq!(
+            event_line(12.344, &event),
+            "t=12.34 merge id=42 id=77 rel_speed=3.21"
+        );
+        let nova = CollisionEvent {
+            kind: CollisionKind::Supernova,
+            ids: (5, 5),
+            ..event
+        };
+        assert_eq!(event_line(1.0, &nova), "t=1.00 supernova id=5");
+    }
+
+    #[test]
+    fn test_fps_stats() {
+        assert_eq!(fps_stats(&VecDeque::new()), None);
+        let frame_times = VecDeque::from(vec![0.01, 0.02, 0.03, 0.04]);
+        let (min, avg, max) = fps_stats(&frame_times).unwrap();
+        assert_approx_eq!(min, 25.0, 1e-3);
+        assert_approx_eq!(avg, 40.0, 1e-3);
+        assert_approx_eq!(max, 100.0, 1e-3);
+    }
+
+    #[test]
+    fn test_format_sim_time() {
+        assert_eq!(format_sim_time(12.34), "12.3 s");
+        assert_eq!(format_sim_time(75.0), "1 min 15.0 s");
+        assert_eq!(format_sim_time(3723.4), "1 h 02 min 03.4 s");
+    }
 }
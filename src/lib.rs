@@ -0,0 +1,11 @@
+//! The physics of nbody, usable without any window or graphics backend.
+//!
+//! Build with `--no-default-features` to leave out the ggez frontend, e.g. to
+//! drive a `Simulation` from a web page (`--features wasm` when targeting
+//! `wasm32-unknown-unknown`).
+
+pub mod animation;
+pub mod export;
+pub mod galaxy;
+pub mod svg;
+pub mod units;
@@ -1,11 +1,16 @@
 //! n-body simulation
 //! Inspired by the book "The Three Body Problem" by Liu Cixin.
 
+mod config;
 mod game;
-use game::start;
-
-mod galaxy;
+mod sound;
+mod theme;
+use config::Config;
+use game::{run_bench, run_headless, start, Settings, TraceStyle};
+use theme::{HexColor, ThemePreset};
 
+use nbody::galaxy::{ClassCounts, CollisionMode, Imf, Mode};
+use std::path::PathBuf;
 use structopt::StructOpt;
 
 #[derive(Debug, StructOpt)]
@@ -20,17 +25,568 @@ use structopt::StructOpt;
 ///
 /// +/- - faster/slower
 ///
+/// z - slow motion when bodies are about to collide
+///
 /// i/o - zoom in/out.
 ///
-/// t - toggle body traces
+/// q/e - rotate the view counterclockwise/clockwise
+///
+/// c - keep the view centered on the center of mass
+///
+/// t - toggle body traces, with Shift freeze them
+///
+/// [/] - trace points closer together/further apart
+///
+/// l - color traces like their body, as rainbow or in a fixed color
+///
+/// v - color bodies by star class or by speed
+///
+/// h - toggle hud
+///
+/// F4 - toggle total energy plot
+///
+/// F2 - toggle body ids
+///
+/// F3 - toggle frame time graph
+///
+/// F5 - save the traces as SVG to the file given with --svg
+///
+/// m - toggle minimap
+///
+/// r - reset with a new galaxy
+///
+/// R - restart with the galaxy the game started with
+///
+/// j/k - add a sun/remove the lightest body
+///
+/// n - spawn a ring of suns around a pinned sun at the mouse cursor
 ///
-/// q - quit
+/// left click - select a body and show its predicted path
+///
+/// p - pin/unpin the selected body
+///
+/// g - show the tides stretching the selected body
+///
+/// x - toggle lines from every body to the center of mass
+///
+/// F6 - toggle lines between bodies heading for a hit, redder the sooner
+///
+/// u - list the bodies closest to the cursor
+///
+/// arrow keys - nudge the selected body
+///
+/// ./, - hold to grow/shrink the body under the cursor
+///
+/// y - put the selected body and its strongest neighbor on a circular orbit
+///
+/// b/f - highlight the most massive/fastest body, with Shift follow it
+///
+/// F7 - highlight the body with the highest speed it ever reached, with Shift follow it
+///
+/// F1 or ? - show all keybindings, any key closes them
+///
+/// Esc - quit
 struct Opt {
-    #[structopt(short, long, default_value = "3")]
-    suns: u32,
+    #[structopt(subcommand)]
+    command: Option<Command>,
+    /// Read defaults for all options below from this TOML file
+    #[structopt(long, parse(from_os_str))]
+    config: Option<PathBuf>,
+    /// Number of suns [default: 3]
+    #[structopt(short, long)]
+    suns: Option<u32>,
+    /// Suns per star class instead of --suns, like O=1,B=2,G=10,M=50
+    #[structopt(long)]
+    class_counts: Option<ClassCounts>,
+    /// Number of black holes swallowing everything they touch [default: 0]
+    #[structopt(long)]
+    black_holes: Option<u32>,
+    /// Scale the distance at which bodies collide, 0 disables collisions [default: 1.0]
+    #[structopt(long)]
+    collision_scale: Option<f32>,
+    /// What colliding suns do: elastic, merge or fragment [default: elastic]
+    #[structopt(long)]
+    collision: Option<CollisionMode>,
+    /// Impact energy above which suns shatter with --collision fragment [default: 10000]
+    #[structopt(long)]
+    fragment_energy: Option<f32>,
+    /// Gravity falls off with 1/r^p for this p, 2 is Newton's law [default: 2.0]
+    #[structopt(long)]
+    force_exponent: Option<f32>,
+    /// Suns merging beyond this mass go supernova, pushing away their neighbors and leaving a remnant
+    #[structopt(long)]
+    supernova_mass: Option<f32>,
+    /// Let bodies passing each other slowly and closely hand mass over to the heavier one
+    #[structopt(long)]
+    accretion: bool,
+    /// Move all bodies after every step so the center of mass stays at rest at the origin, while no body is pinned
+    #[structopt(long)]
+    lock_barycenter: bool,
+    /// Let close binaries spiral into each other as if radiating gravitational waves, this fast
+    #[structopt(long)]
+    gw_inspiral: Option<f32>,
+    /// Cap the speed of all bodies, keeps close encounters from flinging them away at absurd speeds
+    #[structopt(long)]
+    max_speed: Option<f32>,
+    /// Physics constants: arcade (fast and chaotic) or realistic (long-lived orbits) [default: arcade]
+    #[structopt(long)]
+    mode: Option<Mode>,
+    /// Seed for generating the galaxy, random if not given
+    #[structopt(long)]
+    seed: Option<u64>,
+    /// Physics steps per second [default: 60]
+    #[structopt(long)]
+    fps: Option<u32>,
+    /// Draw frames as fast as possible instead of syncing to the display
+    #[structopt(long)]
+    no_vsync: bool,
+    /// Start paused, to set everything up before it moves
+    #[structopt(long)]
+    paused: bool,
+    /// Write the final state as JSON to this file on exit
+    #[structopt(long, parse(from_os_str))]
+    output: Option<PathBuf>,
+    /// Write the body traces as SVG to this file on exit and with F5
+    #[structopt(long, parse(from_os_str))]
+    svg: Option<PathBuf>,
+    /// Record where every body is as CSV to this file in a run without window, to play back with --replay
+    #[structopt(long, parse(from_os_str))]
+    trajectory: Option<PathBuf>,
+    /// Simulated seconds between two samples of --trajectory [default: 0.1]
+    #[structopt(long)]
+    trajectory_interval: Option<f32>,
+    /// Play back a trajectory recorded with --trajectory instead of simulating
+    #[structopt(long, parse(from_os_str))]
+    replay: Option<PathBuf>,
+    /// Start a replay over at its end instead of stopping there
+    #[structopt(long)]
+    replay_loop: bool,
+    /// Save the state every this many simulated seconds to resume from with --load [default: never]
+    #[structopt(long)]
+    checkpoint_interval: Option<f32>,
+    /// Number of checkpoints kept, checkpoint_0.json is the latest [default: 3]
+    #[structopt(long)]
+    checkpoint_keep: Option<usize>,
+    /// Directory checkpoints are written to [default: .]
+    #[structopt(long, parse(from_os_str))]
+    checkpoint_dir: Option<PathBuf>,
+    /// Run the same galaxy a second time next to this one, with the options of this TOML file on top
+    #[structopt(long, parse(from_os_str))]
+    compare: Option<PathBuf>,
+    /// Start from the bodies and camera saved with --output
+    #[structopt(long, parse(from_os_str))]
+    load: Option<PathBuf>,
+    /// Radius of the area suns are spawned in [default: window height / 20 per sun]
+    #[structopt(long)]
+    galaxy_radius: Option<f32>,
+    /// Highest starting speed of the suns [default: set by --mode]
+    #[structopt(long)]
+    max_velocity: Option<f32>,
+    /// Start the suns nearly at rest, a hundredth of --max-velocity, so the cloud collapses
+    #[structopt(long)]
+    cold: bool,
+    /// Mass distribution of new suns: skewed, kroupa, salpeter or uniform [default: skewed]
+    #[structopt(long)]
+    imf: Option<Imf>,
+    /// Colors of background, hud and traces: dark, midnight or paper [default: dark]
+    #[structopt(long)]
+    theme: Option<ThemePreset>,
+    /// Background color as RGB(A) hex like 101820 [default: set by --theme]
+    #[structopt(long)]
+    background: Option<HexColor>,
+    /// Color of the traces: body, rainbow or an RGB(A) hex color like ffffff60 [default: set by --theme]
+    #[structopt(long)]
+    trace_style: Option<TraceStyle>,
+    /// Steps between two trace points at normal speed, scaled with the speed set by +/- [default: 10]
+    #[structopt(long)]
+    trace_interval: Option<u32>,
+    /// Trace points shared by all bodies, traces get shorter the more bodies there are [default: no limit]
+    #[structopt(long)]
+    trace_budget: Option<usize>,
+    /// Draw suns with a soft glow growing with their mass, costs some frame rate
+    #[structopt(long)]
+    glow: bool,
+    /// Draw suns as a glowing star sprite tinted by their color instead of as flat discs
+    #[structopt(long)]
+    sprites: bool,
+    /// Shade the background by the depth of the gravitational potential, costs some frame rate
+    #[structopt(long)]
+    potential_field: bool,
+    /// Cells across the window of the grid --potential-field is sampled on [default: 64]
+    #[structopt(long)]
+    potential_grid: Option<u32>,
+    /// Smoothness of the circles bodies are drawn with, higher is smoother but slower [default: 1]
+    #[structopt(long)]
+    circle_quality: Option<f32>,
+    /// Lean the plane back by this many degrees, like a disk seen at an angle [default: 0]
+    #[structopt(long)]
+    tilt: Option<f32>,
+    /// Share of the way to its target the zoom moves per frame, 1 zooms instantly [default: 0.1]
+    #[structopt(long)]
+    zoom_smoothing: Option<f32>,
+    /// Share of the way to its target the camera pans per frame, 1 pans instantly [default: 0.1]
+    #[structopt(long)]
+    pan_smoothing: Option<f32>,
+    /// Shake the camera briefly on hard hits in view
+    #[structopt(long)]
+    shake: bool,
+    /// Play a thump on collisions, louder the harder the hit
+    #[structopt(long)]
+    sound: bool,
+    /// Stress test: keep spawning this many suns per second, up to --max-bodies
+    #[structopt(long)]
+    spawn_rate: Option<f32>,
+    /// Number of bodies at which --spawn-rate stops spawning [default: 500]
+    #[structopt(long)]
+    max_bodies: Option<u32>,
+    /// Number of suns in a ring spawned with n [default: 8]
+    #[structopt(long)]
+    ring_count: Option<u32>,
+    /// Radius of a ring spawned with n [default: 150]
+    #[structopt(long)]
+    ring_radius: Option<f32>,
+    /// Mass of the pinned sun in the middle of a ring, a ring sun weighs 0.3 [default: 100]
+    #[structopt(long)]
+    ring_mass: Option<f32>,
+    /// Speed gain in a close pass, as share of the speed before, for which a body is annotated as slingshot [default: 0.25]
+    #[structopt(long)]
+    slingshot_threshold: Option<f32>,
+    /// Let a new galaxy keep the drift of its random starting velocities, also when bodies are added or removed later
+    #[structopt(long)]
+    no_recenter_momentum: bool,
+    /// Pin the heaviest body in place as a central star, also after r
+    #[structopt(long)]
+    pin_heaviest: bool,
+    /// Record a run without window as animated GIF to this file
+    #[structopt(long, parse(from_os_str))]
+    gif: Option<PathBuf>,
+    /// Number of frames in the GIF, spread over --steps if given [default: 120]
+    #[structopt(long)]
+    gif_frames: Option<u32>,
+    /// Shrink the GIF to 1/n of the window size [default: 2]
+    #[structopt(long)]
+    gif_scale: Option<u32>,
+    /// Fly the camera through keyframes in a CSV file with the columns time,x,y,zoom
+    #[structopt(long, parse(from_os_str))]
+    camera_path: Option<PathBuf>,
+    /// Start from suns in a CSV file with the columns mass,x,y,vx,vy
+    #[structopt(long, parse(from_os_str))]
+    catalog: Option<PathBuf>,
+    /// Set the speed so the two heaviest bodies orbit each other once in this many seconds
+    #[structopt(long)]
+    period_seconds: Option<f32>,
+    /// Number of bodies listed next to the cursor after pressing u [default: 3]
+    #[structopt(long)]
+    inspect_count: Option<usize>,
+    /// Show masses in the hud in solar masses, this many per simulation mass [default: 1.0]
+    #[structopt(long)]
+    unit_mass: Option<f32>,
+    /// Show lengths in the hud in AU, this many per simulation length [default: 1.0]
+    #[structopt(long)]
+    unit_length: Option<f32>,
+    /// Show times in the hud in years, this many per simulated second [default: 1.0]
+    #[structopt(long)]
+    unit_time: Option<f32>,
+    /// Simulation masses per catalog mass unit [default: 1.0]
+    #[structopt(long)]
+    catalog_mass_unit: Option<f32>,
+    /// Simulation lengths per catalog length unit [default: 1.0]
+    #[structopt(long)]
+    catalog_length_unit: Option<f32>,
+    /// Simulation velocities per catalog velocity unit [default: 1.0]
+    #[structopt(long)]
+    catalog_velocity_unit: Option<f32>,
+    /// Run this many steps without a window, then exit
+    #[structopt(long)]
+    steps: Option<u64>,
+    /// Print a line to stdout for every collision, merge and ejection
+    #[structopt(long)]
+    events: bool,
+    /// Log more details, repeat for even more (-v info, -vv debug, -vvv trace)
+    #[structopt(short, long, parse(from_occurrences))]
+    verbose: u8,
+}
+
+#[derive(Debug, StructOpt)]
+enum Command {
+    /// Step the simulation without window as fast as possible and print
+    /// key=value lines with the steps per second and the final energy
+    Bench {
+        /// Number of suns [default: --suns]
+        #[structopt(long)]
+        suns: Option<u32>,
+        /// Number of steps to run
+        #[structopt(long, default_value = "500")]
+        steps: u64,
+    },
+}
+
+impl Opt {
+    /// The options given on the command line, `None` for all others.
+    fn to_config(&self) -> Config {
+        Config {
+            suns: self.suns,
+            class_counts: self.class_counts.clone(),
+            black_holes: self.black_holes,
+            collision_scale: self.collision_scale,
+            collision: self.collision,
+            fragment_energy: self.fragment_energy,
+            force_exponent: self.force_exponent,
+            supernova_mass: self.supernova_mass,
+            accretion: if self.accretion { Some(true) } else { None },
+            lock_barycenter: if self.lock_barycenter {
+                Some(true)
+            } else {
+                None
+            },
+            max_speed: self.max_speed,
+            gw_inspiral: self.gw_inspiral,
+            mode: self.mode,
+            seed: self.seed,
+            fps: self.fps,
+            vsync: if self.no_vsync { Some(false) } else { None },
+            paused: if self.paused { Some(true) } else { None },
+            output: self.output.clone(),
+            svg: self.svg.clone(),
+            trajectory: self.trajectory.clone(),
+            trajectory_interval: self.trajectory_interval,
+            replay: self.replay.clone(),
+            replay_loop: if self.replay_loop { Some(true) } else { None },
+            compare: self.compare.clone(),
+            checkpoint_interval: self.checkpoint_interval,
+            checkpoint_keep: self.checkpoint_keep,
+            checkpoint_dir: self.checkpoint_dir.clone(),
+            load: self.load.clone(),
+            catalog: self.catalog.clone(),
+            period_seconds: self.period_seconds,
+            inspect_count: self.inspect_count,
+            unit_mass: self.unit_mass,
+            unit_length: self.unit_length,
+            unit_time: self.unit_time,
+            catalog_mass_unit: self.catalog_mass_unit,
+            catalog_length_unit: self.catalog_length_unit,
+            catalog_velocity_unit: self.catalog_velocity_unit,
+            steps: self.steps,
+            events: if self.events { Some(true) } else { None },
+            trace_style: self.trace_style,
+            trace_interval: self.trace_interval,
+            trace_budget: self.trace_budget,
+            glow: if self.glow { Some(true) } else { None },
+            sprites: if self.sprites { Some(true) } else { None },
+            sound: if self.sound { Some(true) } else { None },
+            shake: if self.shake { Some(true) } else { None },
+            potential_field: if self.potential_field {
+                Some(true)
+            } else {
+                None
+            },
+            potential_grid: self.potential_grid,
+            circle_quality: self.circle_quality,
+            tilt: self.tilt,
+            zoom_smoothing: self.zoom_smoothing,
+            pan_smoothing: self.pan_smoothing,
+            spawn_rate: self.spawn_rate,
+            max_bodies: self.max_bodies,
+            theme: self.theme,
+            background: self.background,
+            slingshot_threshold: self.slingshot_threshold,
+            ring_count: self.ring_count,
+            ring_radius: self.ring_radius,
+            ring_mass: self.ring_mass,
+            galaxy_radius: self.galaxy_radius,
+            max_velocity: self.max_velocity,
+            cold: if self.cold { Some(true) } else { None },
+            imf: self.imf,
+            gif: self.gif.clone(),
+            gif_frames: self.gif_frames,
+            gif_scale: self.gif_scale,
+            camera_path: self.camera_path.clone(),
+            recenter_momentum: if self.no_recenter_momentum {
+                Some(false)
+            } else {
+                None
+            },
+            pin_heaviest: if self.pin_heaviest { Some(true) } else { None },
+        }
+    }
 }
 
 fn main() -> ggez::GameResult {
     let opt = Opt::from_args();
-    start(opt.suns)
+    let level = match opt.verbose {
+        0 => log::LevelFilter::Warn,
+        1 => log::LevelFilter::Info,
+        2 => log::LevelFilter::Debug,
+        _ => log::LevelFilter::Trace,
+    };
+    env_logger::Builder::new()
+        .filter_level(level)
+        .parse_default_env()
+        .init();
+    let file = match &opt.config {
+        Some(path) => Config::from_file(path).map_err(ggez::GameError::ConfigError)?,
+        None => Config::default(),
+    };
+    let config = opt.to_config().or(file);
+    let (settings, sim_config) = (config.settings(), config.sim_config());
+    if sim_config.collision_scale < 0.0 {
+        return Err(ggez::GameError::ConfigError(
+            "collision scale must not be negative".to_string(),
+        ));
+    }
+    if sim_config.inspiral.is_some_and(|s| s <= 0.0) {
+        return Err(ggez::GameError::ConfigError(
+            "gw inspiral strength must be positive".to_string(),
+        ));
+    }
+    if sim_config.max_speed.is_some_and(|s| s <= 0.0) {
+        return Err(ggez::GameError::ConfigError(
+            "max speed must be positive".to_string(),
+        ));
+    }
+    if sim_config.supernova_mass.is_some_and(|m| m <= 0.0) {
+        return Err(ggez::GameError::ConfigError(
+            "supernova mass must be positive".to_string(),
+        ));
+    }
+    if settings.galaxy_radius.is_some_and(|r| r <= 0.0) {
+        return Err(ggez::GameError::ConfigError(
+            "galaxy radius must be positive".to_string(),
+        ));
+    }
+    if sim_config.profile.max_starting_velocity <= 0.0 {
+        return Err(ggez::GameError::ConfigError(
+            "max velocity must be positive".to_string(),
+        ));
+    }
+    if settings.load.is_some() && settings.catalog.is_some() {
+        return Err(ggez::GameError::ConfigError(
+            "--load and --catalog can not be combined".to_string(),
+        ));
+    }
+    if settings.replay.is_some()
+        && (settings.load.is_some() || settings.catalog.is_some() || config.compare.is_some())
+    {
+        return Err(ggez::GameError::ConfigError(
+            "--replay can not be combined with --load, --catalog or --compare".to_string(),
+        ));
+    }
+    let units = settings.catalog_units;
+    if [units.mass, units.length, units.velocity]
+        .iter()
+        .any(|u| *u <= 0.0)
+    {
+        return Err(ggez::GameError::ConfigError(
+            "catalog units must be positive".to_string(),
+        ));
+    }
+    if settings.period_seconds.is_some_and(|s| s <= 0.0) {
+        return Err(ggez::GameError::ConfigError(
+            "period seconds must be positive".to_string(),
+        ));
+    }
+    if settings.checkpoint_interval.is_some_and(|i| i <= 0.0) || settings.checkpoint_keep == 0 {
+        return Err(ggez::GameError::ConfigError(
+            "checkpoint interval must be positive and at least one checkpoint kept".to_string(),
+        ));
+    }
+    if settings.trajectory_interval <= 0.0 {
+        return Err(ggez::GameError::ConfigError(
+            "trajectory interval must be positive".to_string(),
+        ));
+    }
+    if settings.inspect_count == 0 {
+        return Err(ggez::GameError::ConfigError(
+            "inspect count must be at least 1".to_string(),
+        ));
+    }
+    if let Some(units) = settings.units {
+        if [units.mass, units.length, units.time]
+            .iter()
+            .any(|u| *u <= 0.0)
+        {
+            return Err(ggez::GameError::ConfigError(
+                "display units must be positive".to_string(),
+            ));
+        }
+    }
+    if settings.ring_count == 0 || settings.ring_radius <= 0.0 || settings.ring_mass <= 0.0 {
+        return Err(ggez::GameError::ConfigError(
+            "ring count, radius and mass must be positive".to_string(),
+        ));
+    }
+    if settings.spawn_rate.is_some_and(|r| r <= 0.0) || settings.max_bodies == 0 {
+        return Err(ggez::GameError::ConfigError(
+            "spawn rate and max bodies must be positive".to_string(),
+        ));
+    }
+    if settings.pin_heaviest && sim_config.lock_barycenter {
+        return Err(ggez::GameError::ConfigError(
+            "lock barycenter and pin heaviest exclude each other, the pinned body holds the frame"
+                .to_string(),
+        ));
+    }
+    if settings.circle_quality <= 0.0 {
+        return Err(ggez::GameError::ConfigError(
+            "circle quality must be positive".to_string(),
+        ));
+    }
+    if !(0.0..90.0).contains(&settings.tilt) {
+        return Err(ggez::GameError::ConfigError(
+            "tilt must be at least 0 and below 90 degrees".to_string(),
+        ));
+    }
+    if [settings.zoom_smoothing, settings.pan_smoothing]
+        .iter()
+        .any(|s| *s <= 0.0 || *s > 1.0)
+    {
+        return Err(ggez::GameError::ConfigError(
+            "zoom and pan smoothing must be above 0 and at most 1".to_string(),
+        ));
+    }
+    if settings.potential_grid == 0 {
+        return Err(ggez::GameError::ConfigError(
+            "potential grid must be at least 1".to_string(),
+        ));
+    }
+    if settings.trace_interval == 0 || settings.trace_budget == Some(0) {
+        return Err(ggez::GameError::ConfigError(
+            "trace interval and budget must be at least 1".to_string(),
+        ));
+    }
+    if settings.slingshot_threshold <= 0.0 {
+        return Err(ggez::GameError::ConfigError(
+            "slingshot threshold must be positive".to_string(),
+        ));
+    }
+    if !(1..=1000).contains(&settings.fps) {
+        return Err(ggez::GameError::ConfigError(
+            "fps must be between 1 and 1000".to_string(),
+        ));
+    }
+    if settings.gif_frames == 0 || settings.gif_scale == 0 {
+        return Err(ggez::GameError::ConfigError(
+            "gif frames and scale must be at least 1".to_string(),
+        ));
+    }
+    if let Some(Command::Bench { suns, steps }) = opt.command {
+        let settings = Settings {
+            suns: suns.unwrap_or(settings.suns),
+            ..settings
+        };
+        return run_bench(settings, sim_config, steps, &mut std::io::stdout());
+    }
+    match config.headless_steps() {
+        Some(steps) => run_headless(settings, sim_config, steps),
+        None => {
+            let compare = config.comparison().map_err(ggez::GameError::ConfigError)?;
+            start(
+                Settings {
+                    compare,
+                    ..settings
+                },
+                sim_config,
+            )
+        }
+    }
 }
@@ -0,0 +1,151 @@
+use std::f32::consts::TAU;
+use std::time::Duration;
+
+use ggez::audio::{SoundData, SoundSource, Source};
+use ggez::Context;
+use log::warn;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use nbody::galaxy::CollisionEvent;
+
+const SAMPLE_RATE: u32 = 22_050;
+const IMPACT_LENGTH: f32 = 0.2; // seconds
+const IMPACT_PITCH: f32 = 70.0; // Hz of the thump under the crackle
+const IMPACT_DECAY: f32 = 25.0; // per second
+const IMPACT_NOISE: f32 = 0.3; // share of the crackle
+
+const MIN_GAP: Duration = Duration::from_millis(60); // impacts in between are dropped
+const QUIET_ENERGY: f32 = 1e2; // softer impacts play at the lowest volume
+const LOUD_ENERGY: f32 = 1e7; // harder impacts play at full volume
+const MIN_VOLUME: f32 = 0.05;
+
+/// A short thump as 16 bit mono WAV, made up on the spot so there is no
+/// asset to ship.
+fn impact_wav() -> Vec<u8> {
+    let mut rng = StdRng::seed_from_u64(0);
+    let samples = (IMPACT_LENGTH * SAMPLE_RATE as f32) as u32;
+    let data_len = samples * 2;
+    let mut wav = Vec::with_capacity(44 + data_len as usize);
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+    wav.extend_from_slice(b"WAVEfmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes());
+    wav.extend_from_slice(&1u16.to_le_bytes()); // PCM
+    wav.extend_from_slice(&1u16.to_le_bytes()); // mono
+    wav.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+    wav.extend_from_slice(&(SAMPLE_RATE * 2).to_le_bytes());
+    wav.extend_from_slice(&2u16.to_le_bytes());
+    wav.extend_from_slice(&16u16.to_le_bytes());
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&data_len.to_le_bytes());
+    for i in 0..samples {
+        let t = i as f32 / SAMPLE_RATE as f32;
+        let tone = (TAU * IMPACT_PITCH * t).sin();
+        let noise = rng.gen_range(-1.0..1.0);
+        let s = (-IMPACT_DECAY * t).exp() * ((1.0 - IMPACT_NOISE) * tone + IMPACT_NOISE * noise);
+        wav.extend_from_slice(&((s * i16::MAX as f32) as i16).to_le_bytes());
+    }
+    wav
+}
+
+/// Volume for an impact of `energy`, growing with its logarithm.
+fn impact_volume(energy: f32) -> f32 {
+    let share = (energy.max(QUIET_ENERGY) / QUIET_ENERGY).ln() / (LOUD_ENERGY / QUIET_ENERGY).ln();
+    share.clamp(MIN_VOLUME, 1.0)
+}
+
+/// Lets a sound through at most once every `MIN_GAP`.
+#[derive(Debug, Default)]
+struct Throttle {
+    wait: Duration,
+}
+
+impl Throttle {
+    fn pass(&mut self, elapsed: Duration, wanted: bool) -> bool {
+        self.wait = self.wait.saturating_sub(elapsed);
+        if wanted && self.wait.is_zero() {
+            self.wait = MIN_GAP;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Plays a thump for collisions, as loud as the hardest hit since the last
+/// frame. A pileup makes a steady rumble instead of a wall of sound.
+pub struct ImpactSound {
+    source: Source,
+    throttle: Throttle,
+    pending: Option<f32>,
+}
+
+impl ImpactSound {
+    /// `None` if the sound cannot be played, e.g. without audio device.
+    pub fn new(ctx: &mut Context) -> Option<ImpactSound> {
+        match Source::from_data(ctx, SoundData::from(impact_wav())) {
+            Ok(source) => Some(ImpactSound {
+                source,
+                throttle: Throttle::default(),
+                pending: None,
+            }),
+            Err(e) => {
+                warn!("no collision sounds: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Remember `events` for the next `play`.
+    pub fn add(&mut self, events: &[CollisionEvent]) {
+        for e in events {
+            let volume = impact_volume(e.energy);
+            self.pending = Some(self.pending.map_or(volume, |v| v.max(volume)));
+        }
+    }
+
+    /// Play the impacts added since the last frame, `elapsed` ago.
+    pub fn play(&mut self, ctx: &Context, elapsed: Duration) {
+        let volume = self.pending.take();
+        if self.throttle.pass(elapsed, volume.is_some()) {
+            self.source.set_volume(volume.unwrap_or(MIN_VOLUME));
+            if let Err(e) = self.source.play_detached(ctx) {
+                warn!("failed to play collision sound: {}", e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_impact_wav_decodes() {
+        let wav = impact_wav();
+        assert_eq!(&wav[..4], b"RIFF");
+        assert_eq!(
+            wav.len(),
+            44 + 2 * (IMPACT_LENGTH * SAMPLE_RATE as f32) as usize
+        );
+        assert!(SoundData::from(wav).can_play());
+    }
+
+    #[test]
+    fn test_impact_volume() {
+        assert_eq!(impact_volume(0.0), MIN_VOLUME);
+        assert_eq!(impact_volume(1e12), 1.0);
+        assert!(impact_volume(1e4) < impact_volume(1e5));
+    }
+
+    #[test]
+    fn test_throttle_drops_sounds_in_quick_succession() {
+        let mut throttle = Throttle::default();
+        let frame = Duration::from_millis(16);
+        let played: Vec<bool> = (0..10).map(|_| throttle.pass(frame, true)).collect();
+        assert_eq!(played.iter().filter(|p| **p).count(), 3);
+        assert!(played[0]);
+        assert!(!throttle.pass(Duration::from_secs(1), false));
+    }
+}
@@ -0,0 +1,153 @@
+use std::fmt::Write as _;
+use std::io::{self, Write};
+use std::path::Path;
+
+use super::export::write_atomically;
+use super::galaxy::{Actor, Point2};
+
+/// Nominal width of the picture, strokes are as wide as on screen at this size.
+const SVG_WIDTH: f32 = 1000.0; // pixels
+const SVG_MARGIN: f32 = 0.05; // share of the picture left free around the bodies
+const TRACE_WIDTH: f32 = 1.0; // pixels
+const EMPTY_EXTENT: f32 = 100.0; // world units shown when there is nothing to show
+
+/// `#rrggbb` and the opacity of an RGBA color as stored in `Actor::color`.
+fn svg_color(color: u32) -> (String, f32) {
+    let opacity = (color & 0xff) as f32 / 255.0;
+    (format!("#{:06x}", color >> 8), opacity)
+}
+
+/// The smallest box around all bodies and their traces as
+/// `(min_x, min_y, width, height)` in world coordinates, with a margin.
+fn bounds(actors: &[Actor]) -> (f32, f32, f32, f32) {
+    let mut min = Point2::splat(f32::INFINITY);
+    let mut max = Point2::splat(f32::NEG_INFINITY);
+    for a in actors {
+        min = min
+            .min(a.pos - a.radius)
+            .min(a.trace.iter().copied().fold(min, Point2::min));
+        max = max
+            .max(a.pos + a.radius)
+            .max(a.trace.iter().copied().fold(max, Point2::max));
+    }
+    if !min.is_finite() || !max.is_finite() {
+        let half = EMPTY_EXTENT / 2.0;
+        return (-half, -half, EMPTY_EXTENT, EMPTY_EXTENT);
+    }
+    let margin = (max - min).max_element().max(f32::EPSILON) * SVG_MARGIN;
+    let size = max - min + 2.0 * margin;
+    (min.x - margin, min.y - margin, size.x, size.y)
+}
+
+/// Draw the traces and bodies of `actors` as SVG, with world coordinates in
+/// the view box. The y axis points up in the world but down in SVG, so every
+/// y is flipped.
+pub fn to_svg(
+    actors: &[Actor],
+    trace_color: impl Fn(&Actor) -> u32,
+    body_color: impl Fn(&Actor) -> u32,
+) -> String {
+    let (x, y, w, h) = bounds(actors);
+    let stroke = TRACE_WIDTH * w / SVG_WIDTH;
+    let mut svg = String::new();
+    // writing into a String cannot fail
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" viewBox="{} {} {} {}">"#,
+        SVG_WIDTH,
+        (SVG_WIDTH * h / w).round(),
+        x,
+        -(y + h),
+        w,
+        h
+    );
+    for a in actors.iter().filter(|a| a.trace.len() >= 2) {
+        let (color, opacity) = svg_color(trace_color(a));
+        let points: Vec<String> = a
+            .trace
+            .iter()
+            .map(|p| format!("{},{}", p.x, -p.y))
+            .collect();
+        let _ = writeln!(
+            svg,
+            r#"  <polyline points="{}" fill="none" stroke="{}" stroke-opacity="{}" stroke-width="{}"/>"#,
+            points.join(" "),
+            color,
+            opacity,
+            stroke
+        );
+    }
+    for a in actors {
+        let (color, opacity) = svg_color(body_color(a));
+        let _ = writeln!(
+            svg,
+            r#"  <circle cx="{}" cy="{}" r="{}" fill="{}" fill-opacity="{}"/>"#,
+            a.pos.x, -a.pos.y, a.radius, color, opacity
+        );
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Write `to_svg` of `actors` to `path`.
+pub fn write_svg(
+    actors: &[Actor],
+    trace_color: impl Fn(&Actor) -> u32,
+    body_color: impl Fn(&Actor) -> u32,
+    path: &Path,
+) -> io::Result<()> {
+    let svg = to_svg(actors, trace_color, body_color);
+    write_atomically(path, |writer| writer.write_all(svg.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::galaxy::{new_sun, PhysicsProfile};
+    use xml::reader::{EventReader, XmlEvent};
+
+    #[test]
+    fn test_svg_is_well_formed() {
+        let mut traced = new_sun(
+            1,
+            Point2::new(10.0, 20.0),
+            Point2::ZERO,
+            1.0,
+            &PhysicsProfile::ARCADE,
+        );
+        traced.trace.extend([
+            Point2::new(10.0, 20.0),
+            Point2::new(5.0, 15.0),
+            Point2::ZERO,
+        ]);
+        let mut other = new_sun(
+            2,
+            Point2::new(-30.0, 0.0),
+            Point2::ZERO,
+            1.0,
+            &PhysicsProfile::ARCADE,
+        );
+        other
+            .trace
+            .extend([Point2::new(-30.0, 0.0), Point2::new(-25.0, 5.0)]);
+        let untraced = new_sun(
+            3,
+            Point2::new(0.0, -40.0),
+            Point2::ZERO,
+            1.0,
+            &PhysicsProfile::ARCADE,
+        );
+        let svg = to_svg(&[traced, other, untraced], |_| 0xff00_0080, |a| a.color);
+
+        let mut elements = Vec::new();
+        for event in EventReader::new(svg.as_bytes()) {
+            if let XmlEvent::StartElement { name, .. } = event.expect("malformed svg") {
+                elements.push(name.local_name);
+            }
+        }
+        let count = |name: &str| elements.iter().filter(|e| *e == name).count();
+        assert_eq!(elements[0], "svg");
+        assert_eq!((count("polyline"), count("circle")), (2, 3));
+        assert!(svg.contains(r##"stroke="#ff0000" stroke-opacity="0.5019608""##));
+    }
+}
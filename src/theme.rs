@@ -0,0 +1,112 @@
+use serde::Deserialize;
+
+use super::game::TraceStyle;
+
+/// Parse an RGB or RGBA hex color like `ff8000` or `#ff800080` into the
+/// RGBA `u32` the drawing code uses, RGB colors are opaque.
+pub fn parse_hex_color(s: &str) -> Result<u32, String> {
+    let hex = s.strip_prefix('#').unwrap_or(s);
+    let valid = matches!(hex.len(), 6 | 8) && hex.chars().all(|c| c.is_ascii_hexdigit());
+    let color = valid
+        .then(|| u32::from_str_radix(hex, 16).ok())
+        .flatten()
+        .ok_or_else(|| format!("invalid color '{}', expected e.g. ff8000 or ff800080", s))?;
+    Ok(if hex.len() == 6 {
+        color << 8 | 0xff
+    } else {
+        color
+    })
+}
+
+/// A color given as hex string on the command line or in the config file.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct HexColor(pub u32);
+
+impl std::str::FromStr for HexColor {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_hex_color(s).map(HexColor)
+    }
+}
+
+impl TryFrom<String> for HexColor {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+/// Colors of everything but the bodies.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Theme {
+    pub background: u32,
+    pub hud: u32,
+    pub traces: TraceStyle,
+}
+
+/// Themes to pick with `--theme`.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ThemePreset {
+    Dark,
+    Midnight,
+    /// Light background for print and screenshots.
+    Paper,
+}
+
+impl ThemePreset {
+    pub fn theme(self) -> Theme {
+        match self {
+            ThemePreset::Dark => Theme {
+                background: 0x1e28_28ff,
+                hud: 0xe0e0_e0ff,
+                traces: TraceStyle::MatchBody,
+            },
+            ThemePreset::Midnight => Theme {
+                background: 0x0509_14ff,
+                hud: 0x9fb4_ffff,
+                traces: TraceStyle::Fixed(0x6080_ff80),
+            },
+            ThemePreset::Paper => Theme {
+                background: 0xf4f1_e8ff,
+                hud: 0x2020_20ff,
+                traces: TraceStyle::Fixed(0x3030_30a0),
+            },
+        }
+    }
+}
+
+impl std::str::FromStr for ThemePreset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "dark" => Ok(ThemePreset::Dark),
+            "midnight" => Ok(ThemePreset::Midnight),
+            "paper" => Ok(ThemePreset::Paper),
+            _ => Err(format!(
+                "unknown theme '{}', expected dark, midnight or paper",
+                s
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_hex_color() {
+        assert_eq!(parse_hex_color("ff8000"), Ok(0xff80_00ff));
+        assert_eq!(parse_hex_color("#ff8000"), Ok(0xff80_00ff));
+        assert_eq!(parse_hex_color("10203040"), Ok(0x1020_3040));
+        assert_eq!(parse_hex_color("#FFFFFF"), Ok(0xffff_ffff));
+        assert!(parse_hex_color("fff").is_err());
+        assert!(parse_hex_color("ggffff").is_err());
+        assert!(parse_hex_color("+fffff").is_err());
+    }
+}
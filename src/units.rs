@@ -0,0 +1,93 @@
+/// Factors from simulation units to physical ones. Only the display uses
+/// them, the simulation itself stays in its own units.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UnitSystem {
+    /// Solar masses per unit of mass.
+    pub mass: f32,
+    /// Astronomical units per unit of length.
+    pub length: f32,
+    /// Years per simulated second.
+    pub time: f32,
+}
+
+impl Default for UnitSystem {
+    fn default() -> Self {
+        // suns are spawned with masses in solar masses already
+        UnitSystem {
+            mass: 1.0,
+            length: 1.0,
+            time: 1.0,
+        }
+    }
+}
+
+impl UnitSystem {
+    pub fn to_solar_masses(self, mass: f32) -> f32 {
+        mass * self.mass
+    }
+
+    pub fn sim_mass(self, solar_masses: f32) -> f32 {
+        solar_masses / self.mass
+    }
+
+    pub fn to_au(self, length: f32) -> f32 {
+        length * self.length
+    }
+
+    pub fn sim_length(self, au: f32) -> f32 {
+        au / self.length
+    }
+
+    pub fn to_years(self, seconds: f32) -> f32 {
+        seconds * self.time
+    }
+
+    pub fn sim_time(self, years: f32) -> f32 {
+        years / self.time
+    }
+
+    pub fn to_au_per_year(self, speed: f32) -> f32 {
+        speed * self.length / self.time
+    }
+
+    /// The hud font has no ☉, hence Msun.
+    pub fn format_mass(self, mass: f32) -> String {
+        format!("{:.2} Msun", self.to_solar_masses(mass))
+    }
+
+    pub fn format_length(self, length: f32) -> String {
+        format!("{:.2} AU", self.to_au(length))
+    }
+
+    pub fn format_time(self, seconds: f32) -> String {
+        format!("{:.2} yr", self.to_years(seconds))
+    }
+
+    pub fn format_speed(self, speed: f32) -> String {
+        format!("{:.2} AU/yr", self.to_au_per_year(speed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_approx_eq::assert_approx_eq;
+
+    #[test]
+    fn test_conversions_round_trip() {
+        let units = UnitSystem {
+            mass: 0.5,
+            length: 0.01,
+            time: 3.0,
+        };
+        for x in [0.0, 0.3, 1.0, 60.0, 1234.5] {
+            assert_approx_eq!(units.sim_mass(units.to_solar_masses(x)), x);
+            assert_approx_eq!(units.sim_length(units.to_au(x)), x, 1e-3);
+            assert_approx_eq!(units.sim_time(units.to_years(x)), x, 1e-3);
+        }
+        assert_eq!(units.to_solar_masses(2.0), 1.0);
+        assert_approx_eq!(units.to_au_per_year(300.0), 1.0);
+        assert_eq!(units.format_mass(2.0), "1.00 Msun");
+        assert_eq!(UnitSystem::default().format_length(1.5), "1.50 AU");
+    }
+}